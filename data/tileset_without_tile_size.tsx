@@ -0,0 +1,4 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset name="Sizeless" tilecount="4" columns="2">
+ <image source="sizeless.png" width="32" height="32"/>
+</tileset>
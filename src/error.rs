@@ -19,7 +19,7 @@ use std::io;
 
 #[derive(Debug)]
 pub enum Error {
-    BadXml,
+    BadXml(String),
     BadAxis(String),
     BadIndex(String),
     BadOrientation(String),
@@ -27,18 +27,36 @@ pub enum Error {
     BadRenderOrder(String),
     BadDrawOrder(String),
     BadProbability(f32),
+    BadFileExtension(String),
+    BadAlignment(String),
+    BadImageFormat(String),
+    BadBool(String),
+    BadEncoding(String),
+    BadCompression(String),
+    BadTileData(String),
+    DuplicateImage(String),
+    DuplicateObjectId(u32),
     UnknownAttribute(String),
+    UnexpectedAttribute(String),
+    MissingAttribute(String),
     InvalidColor(String),
     InvalidNumber(String),
     InvalidPoint(String),
     InvalidTerrain(String),
+    Decompression(io::Error),
     Io(io::Error),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::BadXml => write!(f, "Invalid XML input"),
+            Error::BadXml(ref element) => {
+                if element.is_empty() {
+                    write!(f, "Invalid XML input")
+                } else {
+                    write!(f, "Invalid XML input: unexpected element `{}`", element)
+                }
+            }
             Error::BadAxis(ref value) => {
                 write!(f,
                        "Illegal value `{}` for the `staggeraxis` attribute",
@@ -74,11 +92,41 @@ impl fmt::Display for Error {
                        "Illegal value `{}` for the `probability` attribute",
                        value)
             }
+            Error::BadFileExtension(ref value) => {
+                write!(f, "Unsupported file extension `{}`", value)
+            }
+            Error::BadAlignment(ref value) => {
+                write!(f,
+                       "Illegal value `{}` for the `halign` or `valign` attribute",
+                       value)
+            }
+            Error::BadImageFormat(ref value) => {
+                write!(f,
+                       "Illegal value `{}` for the `format` attribute",
+                       value)
+            }
+            Error::BadBool(ref value) => write!(f, "Invalid boolean: `{}`", value),
+            Error::BadEncoding(ref value) => {
+                write!(f,
+                       "Illegal value `{}` for the `encoding` attribute",
+                       value)
+            }
+            Error::BadCompression(ref value) => {
+                write!(f,
+                       "Illegal value `{}` for the `compression` attribute",
+                       value)
+            }
+            Error::BadTileData(ref data) => write!(f, "Invalid tile data: `{}`", data),
+            Error::DuplicateImage(ref source) => write!(f, "Duplicate image: `{}`", source),
+            Error::DuplicateObjectId(id) => write!(f, "Duplicate object id: {}", id),
             Error::UnknownAttribute(ref attr) => write!(f, "Unknown attribute: `{}`", attr),
+            Error::UnexpectedAttribute(ref attr) => write!(f, "Unexpected attribute: `{}`", attr),
+            Error::MissingAttribute(ref attr) => write!(f, "Missing attribute: `{}`", attr),
             Error::InvalidColor(ref color) => write!(f, "Invalid color: `{}`", color),
             Error::InvalidNumber(ref num) => write!(f, "Invalid number: `{}`", num),
             Error::InvalidPoint(ref point) => write!(f, "Invalid point: `{}`", point),
             Error::InvalidTerrain(ref terrain) => write!(f, "Invalid terrain: `{}`", terrain),
+            Error::Decompression(ref err) => write!(f, "Decompression error: {}", err),
             Error::Io(ref err) => write!(f, "I/O error: {}", err),
         }
     }
@@ -87,7 +135,7 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
-            Error::BadXml => "Invalid XML input",
+            Error::BadXml(..) => "Invalid XML input",
             Error::BadAxis(..) => "Bad axis value",
             Error::BadIndex(..) => "Bad index value",
             Error::BadOrientation(..) => "Bad orientation value",
@@ -95,11 +143,23 @@ impl error::Error for Error {
             Error::BadRenderOrder(..) => "Bad renderorder value",
             Error::BadDrawOrder(..) => "Bad draworder value",
             Error::BadProbability(..) => "Bad probability value",
+            Error::BadFileExtension(..) => "Bad file extension",
+            Error::BadAlignment(..) => "Bad alignment value",
+            Error::BadImageFormat(..) => "Bad image format value",
+            Error::BadBool(..) => "Bad boolean value",
+            Error::BadEncoding(..) => "Bad encoding value",
+            Error::BadCompression(..) => "Bad compression value",
+            Error::BadTileData(..) => "Bad tile data",
+            Error::DuplicateImage(..) => "Duplicate image",
+            Error::DuplicateObjectId(..) => "Duplicate object id",
             Error::UnknownAttribute(..) => "Unknown attribute",
+            Error::UnexpectedAttribute(..) => "Unexpected attribute",
+            Error::MissingAttribute(..) => "Missing attribute",
             Error::InvalidColor(..) => "Invalid color",
             Error::InvalidNumber(..) => "Invalid number",
             Error::InvalidPoint(..) => "Invalid point",
             Error::InvalidTerrain(..) => "Invalid terrain",
+            Error::Decompression(ref err) => err.description(),
             Error::Io(ref err) => err.description(),
         }
     }
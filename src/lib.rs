@@ -53,8 +53,15 @@
 //! let empty_map = tmx::Map::from_str(r#"<map version="1.0"/>"#);
 //! ```
 
+extern crate flate2;
 extern crate xml;
 
+#[cfg(feature = "rand")]
+extern crate rand;
+
+#[cfg(feature = "zstd")]
+extern crate zstd;
+
 #[cfg(test)]
 #[macro_use] extern crate assert_matches;
 
@@ -64,5 +71,20 @@ mod model;
 pub use error::Error;
 pub use model::*;
 
+/// The result of reading a Tiled file.
+///
+/// ```
+/// extern crate tmx;
+///
+/// use std::str::FromStr;
+///
+/// fn read_map(s: &str) -> tmx::Result<tmx::Map> {
+///     tmx::Map::from_str(s)
+/// }
+///
+/// fn main() {
+///     assert!(read_map(r#"<map version="1.0"/>"#).is_ok());
+/// }
+/// ```
 pub type Result<T> = std::result::Result<T, ::error::Error>;
 
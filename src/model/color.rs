@@ -13,13 +13,50 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt;
 use std::str::FromStr;
 
 use error::Error;
 
+/// A color with its components in Tiled's `#AARRGGBB` order: alpha, red,
+/// green and blue.
 #[derive(Debug, PartialEq, Eq)]
 pub struct Color(pub u8, pub u8, pub u8, pub u8);
 
+impl Color {
+    pub fn alpha(&self) -> u8 {
+        self.0
+    }
+
+    pub fn red(&self) -> u8 {
+        self.1
+    }
+
+    pub fn green(&self) -> u8 {
+        self.2
+    }
+
+    pub fn blue(&self) -> u8 {
+        self.3
+    }
+
+    /// Formats the color as `#RRGGBB`, or `#AARRGGBB` when it is not opaque.
+    pub fn to_hex(&self) -> String {
+        let Color(a, r, g, b) = *self;
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", a, r, g, b)
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
 impl FromStr for Color {
     type Err = Error;
 
@@ -112,4 +149,23 @@ mod tests {
         assert!(Color::from_str("00010204").is_err());
         assert!(Color::from_str("#00010204").is_ok());
     }
+
+    #[test]
+    fn test_color_components_in_argb_order() {
+        let color = Color::from_str("#80ff0000").unwrap();
+        assert_eq!(0x80, color.alpha());
+        assert_eq!(0xff, color.red());
+        assert_eq!(0, color.green());
+        assert_eq!(0, color.blue());
+
+        let color = Color::from_str("ff00aa").unwrap();
+        assert_eq!((255, 255, 0, 0xaa), (color.alpha(), color.red(), color.green(), color.blue()));
+    }
+
+    #[test]
+    fn test_color_to_hex() {
+        assert_eq!("#80ff0000", Color::from_str("#80ff0000").unwrap().to_hex());
+        assert_eq!("#010204", Color::from_str("010204").unwrap().to_hex());
+        assert_eq!("#010204", Color(255, 1, 2, 4).to_string());
+    }
 }
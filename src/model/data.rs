@@ -13,14 +13,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::OnceCell;
 use std::io::Read;
 
 use xml::attribute::OwnedAttribute;
 
 use error::Error;
+use model::encoding;
+use model::gid::TileGid;
 use model::reader::{self, TmxReader, ElementReader};
 
 define_iterator_wrapper!(DataTiles, DataTile);
+define_iterator_wrapper!(Chunks, Chunk);
 
 #[derive(Debug, Default)]
 pub struct Data {
@@ -28,6 +32,8 @@ pub struct Data {
     compression: Option<String>,
     raw: Option<String>,
     tiles: Vec<DataTile>,
+    decoded: OnceCell<Vec<DataTile>>,
+    chunks: Vec<Chunk>,
 }
 
 impl Data {
@@ -55,22 +61,254 @@ impl Data {
         self.raw = Some(content.into());
     }
 
+    /// Returns the tiles of the data. CSV and base64 content is decoded on
+    /// the first call and cached, so later calls reuse the same tiles.
     pub fn tiles(&self) -> DataTiles {
-        DataTiles(self.tiles.iter())
+        DataTiles(self.tile_slice().iter())
+    }
+
+    fn tile_slice(&self) -> &[DataTile] {
+        decoded_tiles(&self.decoded, self.encoding(), self.compression(), self.raw_content(), &self.tiles)
+    }
+
+    /// Returns the tile at `index` in the order of `tiles()`.
+    pub fn tile(&self, index: usize) -> Option<&DataTile> {
+        self.tile_slice().get(index)
+    }
+
+    fn add_tile(&mut self, tile: DataTile) {
+        self.tiles.push(tile);
+    }
+
+    /// Returns `true` once the tiles are available without further decoding.
+    pub fn is_decoded(&self) -> bool {
+        self.encoding.is_none() || self.raw.is_none() || self.decoded.get().is_some()
+    }
+
+    pub fn chunks(&self) -> Chunks {
+        Chunks(self.chunks.iter())
+    }
+
+    /// Returns an iterator over the chunks holding at least one tile, skipping
+    /// the all-zero chunks Tiled sometimes writes for infinite maps.
+    pub fn non_empty_chunks(&self) -> NonEmptyChunks {
+        NonEmptyChunks(self.chunks.iter())
+    }
+
+    fn add_chunk(&mut self, mut chunk: Chunk) {
+        chunk.encoding = self.encoding.clone();
+        chunk.compression = self.compression.clone();
+        self.chunks.push(chunk);
+    }
+
+    /// Decodes the CSV or base64 content of the data and of its chunks into
+    /// tiles, reporting any error in the content.
+    ///
+    /// This is done while reading, unless tile data decoding was disabled on
+    /// the reader, in which case `tiles()` decodes on first access instead.
+    pub fn decode(&self) -> ::Result<()> {
+        try!(decode_into(&self.decoded, self.encoding(), self.compression(), self.raw_content()));
+        for chunk in &self.chunks {
+            try!(chunk.decode());
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over the tiles of all chunks, yielding
+    /// `(x, y, gid)` triples where `x` and `y` are in world tile coordinates.
+    pub fn iter_global_tiles(&self) -> GlobalTiles {
+        GlobalTiles {
+            chunks: self.chunks.iter(),
+            current: None,
+            index: 0,
+        }
+    }
+}
+
+fn decode_tiles(encoding: &str, compression: Option<&str>, raw: &str) -> ::Result<Vec<DataTile>> {
+    let gids = try!(encoding::decode_tile_data(encoding, compression, raw));
+    Ok(gids.into_iter().map(|gid| DataTile { gid: gid }).collect())
+}
+
+fn decode_into(cell: &OnceCell<Vec<DataTile>>,
+               encoding: Option<&str>,
+               compression: Option<&str>,
+               raw: Option<&str>)
+               -> ::Result<()> {
+    if let (Some(encoding), Some(raw)) = (encoding, raw) {
+        if cell.get().is_none() {
+            let tiles = try!(decode_tiles(encoding, compression, raw));
+            let _ = cell.set(tiles);
+        }
+    }
+    Ok(())
+}
+
+// Undecodable content yields no tiles and is not cached, so that `decode()`
+// still reports the error.
+fn decoded_tiles<'a>(cell: &'a OnceCell<Vec<DataTile>>,
+                     encoding: Option<&str>,
+                     compression: Option<&str>,
+                     raw: Option<&str>,
+                     xml_tiles: &'a [DataTile])
+                     -> &'a [DataTile] {
+    if encoding.is_none() || raw.is_none() {
+        return xml_tiles;
+    }
+    match decode_into(cell, encoding, compression, raw) {
+        Ok(()) => cell.get().map(Vec::as_slice).unwrap_or(&[]),
+        Err(_) => &[],
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    encoding: Option<String>,
+    compression: Option<String>,
+    raw: Option<String>,
+    tiles: Vec<DataTile>,
+    decoded: OnceCell<Vec<DataTile>>,
+}
+
+impl Chunk {
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    fn set_x(&mut self, x: i32) {
+        self.x = x;
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    fn set_y(&mut self, y: i32) {
+        self.y = y;
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn set_width(&mut self, width: u32) {
+        self.width = width;
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_height(&mut self, height: u32) {
+        self.height = height;
+    }
+
+    pub fn tiles(&self) -> DataTiles {
+        DataTiles(self.tile_slice().iter())
+    }
+
+    fn tile_slice(&self) -> &[DataTile] {
+        decoded_tiles(&self.decoded,
+                      self.encoding.as_ref().map(String::as_str),
+                      self.compression.as_ref().map(String::as_str),
+                      self.raw_content(),
+                      &self.tiles)
+    }
+
+    /// Returns the tile at `(x, y)` in world tile coordinates, if it lies
+    /// within the chunk.
+    pub fn tile_at(&self, x: i32, y: i32) -> Option<&DataTile> {
+        let (dx, dy) = (x - self.x, y - self.y);
+        if dx < 0 || dy < 0 || dx >= self.width as i32 || dy >= self.height as i32 {
+            return None;
+        }
+        self.tile_slice().get(dy as usize * self.width as usize + dx as usize)
+    }
+
+    /// Returns `true` when every tile of the chunk is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tile_slice().iter().all(|tile| tile.gid == 0)
     }
 
     fn add_tile(&mut self, tile: DataTile) {
         self.tiles.push(tile);
     }
+
+    fn decode(&self) -> ::Result<()> {
+        decode_into(&self.decoded,
+                    self.encoding.as_ref().map(String::as_str),
+                    self.compression.as_ref().map(String::as_str),
+                    self.raw_content())
+    }
+
+    pub fn raw_content(&self) -> Option<&str> {
+        self.raw.as_ref().map(String::as_str)
+    }
+
+    fn set_raw_content<S: Into<String>>(&mut self, content: S) {
+        self.raw = Some(content.into());
+    }
+}
+
+pub struct NonEmptyChunks<'a>(::std::slice::Iter<'a, Chunk>);
+
+impl<'a> Iterator for NonEmptyChunks<'a> {
+    type Item = &'a Chunk;
+
+    fn next(&mut self) -> Option<&'a Chunk> {
+        self.0.by_ref().find(|chunk| !chunk.is_empty())
+    }
+}
+
+/// Cursor over the tiles of a chunked `Data`, hiding chunk boundaries.
+pub struct GlobalTiles<'a> {
+    chunks: ::std::slice::Iter<'a, Chunk>,
+    current: Option<&'a Chunk>,
+    index: usize,
+}
+
+impl<'a> Iterator for GlobalTiles<'a> {
+    type Item = (i32, i32, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chunk) = self.current {
+                let tiles = chunk.tile_slice();
+                if chunk.width > 0 && self.index < tiles.len() {
+                    let width = chunk.width as usize;
+                    let x = chunk.x + (self.index % width) as i32;
+                    let y = chunk.y + (self.index / width) as i32;
+                    let gid = tiles[self.index].gid;
+                    self.index += 1;
+                    return Some((x, y, gid));
+                }
+            }
+            match self.chunks.next() {
+                Some(chunk) => {
+                    self.current = Some(chunk);
+                    self.index = 0;
+                }
+                None => return None,
+            }
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct DataTile {
-   pub gid: i32,
+   pub gid: u32,
 }
 
 impl DataTile {
-    fn set_gid(&mut self, gid: i32) {
+    pub fn tile_gid(&self) -> TileGid {
+        TileGid(self.gid)
+    }
+
+    fn set_gid(&mut self, gid: u32) {
         self.gid = gid;
     }
 }
@@ -92,15 +330,62 @@ impl<R: Read> ElementReader<Data> for TmxReader<R> {
     }
 
     fn read_children(&mut self, data: &mut Data, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
+        match name {
+            "tile" => {
+                let tile = try!(self.on_data_tile(attributes));
+                data.add_tile(tile);
+            }
+            "chunk" => {
+                let chunk = try!(self.on_chunk(attributes));
+                data.add_chunk(chunk);
+            }
+            _ => {}
+        };
+        Ok(())
+    }
+
+    fn read_content(&mut self, data: &mut Data, content: &str) -> ::Result<()> {
+        data.set_raw_content(content);
+        Ok(())
+    }
+}
+
+impl<R: Read> ElementReader<Chunk> for TmxReader<R> {
+    fn read_attributes(&mut self, chunk: &mut Chunk, name: &str, value: &str) -> ::Result<()> {
+        match name {
+            "x" => {
+                let x = try!(reader::read_num(value));
+                chunk.set_x(x);
+            }
+            "y" => {
+                let y = try!(reader::read_num(value));
+                chunk.set_y(y);
+            }
+            "width" => {
+                let width = try!(reader::read_num(value));
+                chunk.set_width(width);
+            }
+            "height" => {
+                let height = try!(reader::read_num(value));
+                chunk.set_height(height);
+            }
+            _ => {
+                return Err(Error::UnknownAttribute(name.to_string()));
+            }
+        };
+        Ok(())
+    }
+
+    fn read_children(&mut self, chunk: &mut Chunk, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
         if name == "tile" {
             let tile = try!(self.on_data_tile(attributes));
-            data.add_tile(tile);
+            chunk.add_tile(tile);
         }
         Ok(())
     }
 
-    fn read_content(&mut self, data: &mut Data, content: &str) -> ::Result<()> {
-        data.set_raw_content(content);
+    fn read_content(&mut self, chunk: &mut Chunk, content: &str) -> ::Result<()> {
+        chunk.set_raw_content(content);
         Ok(())
     }
 }
@@ -0,0 +1,164 @@
+// This file is part of tmx
+// Copyright 2017 Sébastien Watteau
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Read;
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+use error::Error;
+use model::reader;
+
+/// Decodes the global tile ids held by the text content of a `<data>` or
+/// `<chunk>` element.
+pub fn decode_tile_data(encoding: &str, compression: Option<&str>, content: &str) -> ::Result<Vec<u32>> {
+    match encoding {
+        "csv" => {
+            if let Some(compression) = compression {
+                return Err(Error::BadCompression(compression.to_string()));
+            }
+            decode_csv(content)
+        }
+        "base64" => {
+            let bytes = try!(decode_bytes(encoding, compression, content));
+            if bytes.len() % 4 != 0 {
+                return Err(Error::BadTileData(format!("{} bytes of tile data", bytes.len())));
+            }
+            Ok(bytes_to_gids(&bytes))
+        }
+        _ => Err(Error::BadEncoding(encoding.to_string())),
+    }
+}
+
+/// Decodes base64 content, decompressing it if needed, into raw bytes.
+/// This is how images embedded in a `<data>` element are stored.
+pub fn decode_bytes(encoding: &str, compression: Option<&str>, content: &str) -> ::Result<Vec<u8>> {
+    if encoding != "base64" {
+        return Err(Error::BadEncoding(encoding.to_string()));
+    }
+    let bytes = try!(decode_base64(content));
+    decompress(compression, bytes)
+}
+
+fn decode_csv(content: &str) -> ::Result<Vec<u32>> {
+    content.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(reader::read_num)
+        .collect()
+}
+
+fn decompress(compression: Option<&str>, bytes: Vec<u8>) -> ::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    let result = match compression {
+        None => return Ok(bytes),
+        Some("zlib") => ZlibDecoder::new(&bytes[..]).read_to_end(&mut decompressed),
+        Some("gzip") => GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed),
+        #[cfg(feature = "zstd")]
+        Some("zstd") => ::zstd::stream::read::Decoder::new(&bytes[..]).and_then(|mut d| d.read_to_end(&mut decompressed)),
+        Some(other) => return Err(Error::BadCompression(other.to_string())),
+    };
+    try!(result.map_err(Error::Decompression));
+    Ok(decompressed)
+}
+
+fn bytes_to_gids(bytes: &[u8]) -> Vec<u32> {
+    bytes.chunks(4)
+        .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+        .collect()
+}
+
+fn base64_char_to_number(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+fn decode_base64(content: &str) -> ::Result<Vec<u8>> {
+    let input: Vec<u8> = content.bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let data = match input.iter().position(|&c| c == b'=') {
+        Some(padding) => &input[..padding],
+        None => &input[..],
+    };
+    if input.len() % 4 != 0 || input.len() - data.len() > 2 {
+        return Err(Error::BadTileData(content.trim().to_string()));
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &c in data {
+        let n = try!(base64_char_to_number(c).ok_or_else(|| Error::BadTileData(content.trim().to_string())));
+        buffer = buffer << 6 | n as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(b"".to_vec(), decode_base64("").unwrap());
+        assert_eq!(b"f".to_vec(), decode_base64("Zg==").unwrap());
+        assert_eq!(b"fo".to_vec(), decode_base64("Zm8=").unwrap());
+        assert_eq!(b"foobar".to_vec(), decode_base64("  Zm9v\n YmFy ").unwrap());
+        assert!(decode_base64("Zm9").is_err());
+        assert!(decode_base64("Zm9*").is_err());
+    }
+
+    #[test]
+    fn test_decode_csv() {
+        assert_eq!(vec![1, 2, 3, 2147483649], decode_csv("\n1,2,\n3,2147483649\n").unwrap());
+        assert!(decode_csv("1,x").is_err());
+    }
+
+    #[test]
+    fn test_decode_tile_data() {
+        assert_eq!(vec![1, 2], decode_tile_data("base64", None, "AQAAAAIAAAA=").unwrap());
+        assert_eq!(vec![1, 2], decode_tile_data("csv", None, "1,2").unwrap());
+        assert!(decode_tile_data("hex", None, "").is_err());
+        assert!(decode_tile_data("base64", Some("lzma"), "").is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "zstd"))]
+    fn test_decode_zstd_tile_data_without_feature() {
+        match decode_tile_data("base64", Some("zstd"), "") {
+            Err(Error::BadCompression(ref compression)) if compression == "zstd" => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_truncated_tile_data() {
+        match decode_tile_data("base64", None, "AQAAAAI=") {
+            Err(Error::BadTileData(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+// This file is part of tmx
+// Copyright 2017 Sébastien Watteau
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY_FLAG: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY_FLAG: u32 = 0x2000_0000;
+const FLIP_FLAGS: u32 = FLIPPED_HORIZONTALLY_FLAG | FLIPPED_VERTICALLY_FLAG | FLIPPED_DIAGONALLY_FLAG;
+
+/// Returns `gid` with the flip flags cleared, like `TileGid::id`.
+#[inline]
+pub const fn strip_flags(gid: u32) -> u32 {
+    gid & !FLIP_FLAGS
+}
+
+/// Returns the horizontal, vertical and diagonal flip flags of `gid`.
+#[inline]
+pub const fn flip_bits(gid: u32) -> (bool, bool, bool) {
+    (gid & FLIPPED_HORIZONTALLY_FLAG != 0,
+     gid & FLIPPED_VERTICALLY_FLAG != 0,
+     gid & FLIPPED_DIAGONALLY_FLAG != 0)
+}
+
+/// A global tile id as stored in layer data, including the flip flags
+/// held in its highest bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileGid(pub u32);
+
+impl TileGid {
+    /// Returns the global tile id with the flip flags cleared.
+    pub fn id(&self) -> u32 {
+        strip_flags(self.0)
+    }
+
+    pub fn flipped_horizontally(&self) -> bool {
+        self.0 & FLIPPED_HORIZONTALLY_FLAG != 0
+    }
+
+    pub fn flipped_vertically(&self) -> bool {
+        self.0 & FLIPPED_VERTICALLY_FLAG != 0
+    }
+
+    pub fn flipped_diagonally(&self) -> bool {
+        self.0 & FLIPPED_DIAGONALLY_FLAG != 0
+    }
+
+    /// Returns the transform to apply when rendering the tile.
+    pub fn transform(&self) -> Transform {
+        match (self.flipped_horizontally(), self.flipped_vertically(), self.flipped_diagonally()) {
+            (false, false, false) => Transform::Identity,
+            (true, false, false) => Transform::FlipHorizontal,
+            (false, true, false) => Transform::FlipVertical,
+            (true, true, false) => Transform::Rotate180,
+            (false, false, true) => Transform::Transpose,
+            (true, false, true) => Transform::Rotate90,
+            (false, true, true) => Transform::Rotate270,
+            (true, true, true) => Transform::AntiTranspose,
+        }
+    }
+}
+
+impl From<u32> for TileGid {
+    fn from(gid: u32) -> TileGid {
+        TileGid(gid)
+    }
+}
+
+/// The eight transforms that can be expressed with Tiled's flip flags.
+///
+/// The diagonal flag swaps the x and y axes, which combined with the
+/// horizontal and vertical flags gives the rotations by multiples of 90°.
+/// Rotations are clockwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Identity,
+    FlipHorizontal,
+    FlipVertical,
+    Rotate180,
+    Transpose,
+    Rotate90,
+    Rotate270,
+    AntiTranspose,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_gid_flags() {
+        let gid = TileGid(0x8000_0005);
+        assert_eq!(5, gid.id());
+        assert!(gid.flipped_horizontally());
+        assert!(!gid.flipped_vertically());
+        assert!(!gid.flipped_diagonally());
+    }
+
+    #[test]
+    fn test_tile_gid_transform() {
+        assert_eq!(Transform::Identity, TileGid(1).transform());
+        assert_eq!(Transform::FlipHorizontal, TileGid(0x8000_0001).transform());
+        assert_eq!(Transform::FlipVertical, TileGid(0x4000_0001).transform());
+        assert_eq!(Transform::Rotate180, TileGid(0xc000_0001).transform());
+        assert_eq!(Transform::Transpose, TileGid(0x2000_0001).transform());
+        assert_eq!(Transform::Rotate90, TileGid(0xa000_0001).transform());
+        assert_eq!(Transform::Rotate270, TileGid(0x6000_0001).transform());
+        assert_eq!(Transform::AntiTranspose, TileGid(0xe000_0001).transform());
+    }
+
+    #[test]
+    fn test_gid_bit_helpers() {
+        for &raw in &[1, 0x8000_0005, 0x4000_0007, 0x2000_0002, 0xe000_0003] {
+            let gid = TileGid(raw);
+            assert_eq!(gid.id(), strip_flags(raw));
+            assert_eq!((gid.flipped_horizontally(), gid.flipped_vertically(), gid.flipped_diagonally()),
+                       flip_bits(raw));
+        }
+        const STRIPPED: u32 = strip_flags(0xa000_0009);
+        assert_eq!(9, STRIPPED);
+    }
+}
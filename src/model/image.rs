@@ -21,7 +21,12 @@ use xml::attribute::OwnedAttribute;
 use error::Error;
 use model::color::Color;
 use model::data::Data;
-use model::reader::{self, TmxReader, ElementReader};
+use model::encoding;
+use model::reader::{self, ReadMode, TmxReader, ElementReader};
+
+// The formats listed by the TMX documentation, which are the only ones
+// accepted in strict mode.
+const IMAGE_FORMATS: &[&str] = &["png", "gif", "jpg", "bmp"];
 
 #[derive(Debug, Default)]
 pub struct Image {
@@ -74,10 +79,35 @@ impl Image {
         self.height = height;
     }
 
+    /// Returns `false` when the image size was not given in the file, in
+    /// which case it can only be known by loading the image itself.
+    pub fn has_dimensions(&self) -> bool {
+        self.width > 0 && self.height > 0
+    }
+
     pub fn data(&self) -> Option<&Data> {
         self.data.as_ref()
     }
 
+    /// Returns `true` when the image is embedded in the file rather than
+    /// referenced by `source`. `format()` then tells how to decode it.
+    pub fn has_embedded_data(&self) -> bool {
+        self.data.is_some()
+    }
+
+    /// Decodes the embedded image data, honoring its compression. Returns
+    /// `None` when the image is referenced by `source` instead.
+    pub fn decoded_data(&self) -> ::Result<Option<Vec<u8>>> {
+        let data = match self.data {
+            Some(ref data) => data,
+            None => return Ok(None),
+        };
+        let encoding = data.encoding().unwrap_or("");
+        let content = data.raw_content().unwrap_or("");
+        let bytes = try!(encoding::decode_bytes(encoding, data.compression(), content));
+        Ok(Some(bytes))
+    }
+
     fn set_data(&mut self, data: Data) {
         self.data = Some(data);
     }
@@ -87,6 +117,9 @@ impl<R: Read> ElementReader<Image> for TmxReader<R> {
     fn read_attributes(&mut self, image: &mut Image, name: &str, value: &str) -> ::Result<()> {
         match name {
             "format" => {
+                if self.mode() == ReadMode::Strict && !IMAGE_FORMATS.contains(&value) {
+                    return Err(Error::BadImageFormat(value.to_string()));
+                }
                 image.set_format(value);
             }
             "source" => {
@@ -113,6 +146,9 @@ impl<R: Read> ElementReader<Image> for TmxReader<R> {
 
     fn read_children(&mut self, image: &mut Image, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
         if let "data" = name {
+            if image.format().is_empty() {
+                return Err(Error::BadImageFormat(String::new()));
+            }
             let data = try!(self.on_data(attributes));
             image.set_data(data);
         }
@@ -0,0 +1,51 @@
+// This file is part of tmx
+// Copyright 2017 Sébastien Watteau
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::BufRead;
+use std::path::Path;
+
+use error::Error;
+use model::map::{Map, ObjectGroup};
+use model::reader::{self, TmxReader};
+use model::tileset::Tileset;
+
+#[derive(Debug)]
+pub enum Loaded {
+    Map(Map),
+    Tileset(Tileset),
+}
+
+/// Reads a map or a tileset depending on the extension of `path`.
+///
+/// `.tmx` and `.xml` files are read as maps, `.tsx` files as tilesets.
+pub fn load<P: AsRef<Path>>(path: P) -> ::Result<Loaded> {
+    let path = path.as_ref();
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "tmx" | "xml" => Map::open(path).map(Loaded::Map),
+        "tsx" => Tileset::open(path).map(Loaded::Tileset),
+        _ => Err(Error::BadFileExtension(extension)),
+    }
+}
+
+/// Reads only the object groups of the map in `source`, e.g. for collision
+/// data, without building layers or tilesets.
+pub fn object_groups_from_reader<R: BufRead>(source: R) -> ::Result<Vec<ObjectGroup>> {
+    let source = try!(reader::utf8_source(source));
+    TmxReader::new(source).read_object_groups()
+}
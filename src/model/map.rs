@@ -13,32 +13,54 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::io::Read;
+//! Maps and the layers they hold.
+//!
+//! Layers and objects expose their optional `name` attribute twice:
+//! `name()` returns an empty string when it is absent, while `name_opt()`
+//! returns `None`.
+
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::io::{BufRead, BufReader, Read};
 use std::str::FromStr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs::File;
 
 use xml::attribute::OwnedAttribute;
 
 use error::Error;
 use model::color::Color;
-use model::data::Data;
+use model::data::{Chunk, Data};
+use model::gid::TileGid;
 use model::image::Image;
-use model::property::{PropertyCollection, Properties};
-use model::reader::{self, TmxReader, ElementReader};
+use model::property::{ClassRegistry, Property, Properties};
+use model::reader::{self, ReadMode, TmxReader, ElementReader};
 use model::shape::Shape;
-use model::tileset::{Tileset};
+use model::tileset::{Tile, Tileset};
 
 define_iterator_wrapper!(Tilesets, Tileset);
 define_iterator_wrapper!(Layers, Layer);
 define_iterator_wrapper!(ImageLayers, ImageLayer);
 define_iterator_wrapper!(ObjectGroups, ObjectGroup);
 define_iterator_wrapper!(Objects, Object);
+define_iterator_wrapper!(Groups, Group);
+
+pub struct ObjectGroupsMut<'a>(::std::slice::IterMut<'a, ObjectGroup>);
+
+impl<'a> Iterator for ObjectGroupsMut<'a> {
+    type Item = &'a mut ObjectGroup;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct Map {
     bg_color: Option<Color>,
     version: String,
+    class: Option<String>,
     orientation: Orientation,
     render_order: RenderOrder,
     width: u32,
@@ -49,20 +71,76 @@ pub struct Map {
     stagger_axis: Option<Axis>,
     stagger_index: Option<Index>,
     next_object_id: u32,
-    properties: PropertyCollection,
+    next_layer_id: u32,
+    repeat: (bool, bool),
+    infinite: bool,
+    properties: Properties,
     tilesets: Vec<Tileset>,
     layers: Vec<Layer>,
     image_layers: Vec<ImageLayer>,
     object_groups: Vec<ObjectGroup>,
+    groups: Vec<Group>,
+    layer_order: Vec<LayerSlot>,
 }
 
 impl Map {
+    /// Creates an empty orthogonal map with a right-down render order, to
+    /// be filled programmatically rather than read from a file.
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Map {
+        Map {
+            width: width,
+            height: height,
+            tile_width: tile_width,
+            tile_height: tile_height,
+            next_object_id: 1,
+            next_layer_id: 1,
+            ..Map::default()
+        }
+    }
+
     pub fn open<P: AsRef<Path>>(path: P) -> ::Result<Map> {
         let file = try!(File::open(path));
-        let mut reader = TmxReader::new(file);
+        Map::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader<R: BufRead>(source: R) -> ::Result<Map> {
+        Map::from_reader_with_mode(source, ReadMode::default())
+    }
+
+    pub fn from_reader_with_mode<R: BufRead>(source: R, mode: ReadMode) -> ::Result<Map> {
+        let source = try!(reader::utf8_source(source));
+        let mut reader = TmxReader::with_mode(source, mode);
         reader.read_map()
     }
 
+    /// Reads a map from an in-memory buffer, such as one obtained with
+    /// `include_bytes!`. UTF-16 content is detected like in `from_reader`.
+    pub fn from_bytes(bytes: &[u8]) -> ::Result<Map> {
+        Map::from_reader(bytes)
+    }
+
+    pub fn from_str_with_mode(s: &str, mode: ReadMode) -> ::Result<Map> {
+        Map::from_reader_with_mode(s.as_bytes(), mode)
+    }
+
+    /// Reads a map in lenient mode, skipping invalid attributes.
+    ///
+    /// Returns the map, if it could be read at all, along with all the
+    /// errors encountered.
+    pub fn from_str_collect_errors(s: &str) -> (Option<Map>, Vec<Error>) {
+        let mut reader = TmxReader::with_mode(s.trim_start_matches('\u{feff}').as_bytes(), ReadMode::Lenient);
+        reader.set_collect_errors(true);
+        let result = reader.read_map();
+        let mut errors = reader.take_errors();
+        match result {
+            Ok(map) => (Some(map), errors),
+            Err(err) => {
+                errors.push(err);
+                (None, errors)
+            }
+        }
+    }
+
     pub fn version(&self) -> &str {
         &self.version
     }
@@ -71,6 +149,29 @@ impl Map {
         self.version = version.into();
     }
 
+    /// Parses the version as a `(major, minor)` pair, e.g. `"1.10"` gives
+    /// `(1, 10)`. Returns `None` if the version is not in this form.
+    pub fn version_tuple(&self) -> Option<(u32, u32)> {
+        let mut parts = self.version.split('.');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(major), Some(minor), None) => {
+                match (major.parse(), minor.parse()) {
+                    (Ok(major), Ok(minor)) => Some((major, minor)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_ref().map(String::as_str)
+    }
+
+    fn set_class<S: Into<String>>(&mut self, class: S) {
+        self.class = Some(class.into());
+    }
+
     pub fn orientation(&self) -> Orientation {
         self.orientation
     }
@@ -79,6 +180,20 @@ impl Map {
         self.orientation = orientation;
     }
 
+    /// Guesses the orientation from the other attributes of the map, for
+    /// files where it is missing or wrong: a hex side length implies a
+    /// hexagonal map and stagger settings a staggered one. Otherwise, the
+    /// parsed orientation is returned.
+    pub fn infer_orientation(&self) -> Orientation {
+        if self.hex_side_length.is_some() {
+            Orientation::Hexagonal
+        } else if self.stagger_axis.is_some() || self.stagger_index.is_some() {
+            Orientation::Staggered
+        } else {
+            self.orientation
+        }
+    }
+
     pub fn render_order(&self) -> RenderOrder {
         self.render_order
     }
@@ -119,6 +234,16 @@ impl Map {
         self.tile_height = tile_height;
     }
 
+    /// Returns the map size in tiles, as `(width, height)`.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the tile size in pixels, as `(tile_width, tile_height)`.
+    pub fn tile_dimensions(&self) -> (u32, u32) {
+        (self.tile_width, self.tile_height)
+    }
+
     pub fn hex_side_length(&self) -> Option<u32> {
         self.hex_side_length
     }
@@ -159,14 +284,84 @@ impl Map {
         self.next_object_id = next_object_id;
     }
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+    pub fn next_layer_id(&self) -> u32 {
+        self.next_layer_id
+    }
+
+    fn set_next_layer_id(&mut self, next_layer_id: u32) {
+        self.next_layer_id = next_layer_id;
+    }
+
+    /// Hands out the next layer id, starting from 1 when the map does not
+    /// declare `nextlayerid`.
+    fn allocate_layer_id(&mut self) -> u32 {
+        let id = cmp::max(self.next_layer_id, 1);
+        self.next_layer_id = id + 1;
+        id
+    }
+
+    /// Returns whether the map wraps around horizontally and vertically.
+    ///
+    /// This is read from the nonstandard `repeat`, `repeatx` and `repeaty`
+    /// attributes, which are only accepted in lenient mode.
+    pub fn repeat(&self) -> (bool, bool) {
+        self.repeat
     }
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
+    /// Returns `true` for infinite maps, whose layer data is stored in
+    /// chunks.
+    pub fn is_infinite(&self) -> bool {
+        self.infinite
+    }
+
+    fn set_infinite(&mut self, infinite: bool) {
+        self.infinite = infinite;
+    }
+
+    fn set_repeat_x(&mut self, repeat_x: bool) {
+        self.repeat.0 = repeat_x;
+    }
+
+    fn set_repeat_y(&mut self, repeat_y: bool) {
+        self.repeat.1 = repeat_y;
+    }
+
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    fn set_properties(&mut self, properties: Properties) {
         self.properties = properties;
     }
 
+    /// Returns the value of the map property `name`, if any.
+    pub fn property_str(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(Property::value)
+    }
+
+    /// Returns the map property `name` as an integer, or `None` if it is
+    /// missing or not an integer. The same goes for the other typed getters.
+    pub fn property_int(&self, name: &str) -> Option<i32> {
+        self.properties.get(name).and_then(Property::as_int)
+    }
+
+    pub fn property_float(&self, name: &str) -> Option<f64> {
+        self.properties.get(name).and_then(Property::as_float)
+    }
+
+    pub fn property_bool(&self, name: &str) -> Option<bool> {
+        self.properties.get(name).and_then(Property::as_bool)
+    }
+
+    /// Returns the map properties merged over the defaults of the map's
+    /// class, as found in `registry`.
+    pub fn effective_properties(&self, registry: &ClassRegistry) -> Properties {
+        match self.class().and_then(|class| registry.get(class)) {
+            Some(defaults) => self.properties.merged_over(defaults),
+            None => self.properties.clone(),
+        }
+    }
+
     pub fn tilesets(&self) -> Tilesets {
         Tilesets(self.tilesets.iter())
     }
@@ -175,11 +370,136 @@ impl Map {
         self.tilesets.push(tileset);
     }
 
+    /// Returns the size of the whole map in pixels, computed like Tiled does
+    /// for each orientation.
+    pub fn pixel_size(&self) -> (u32, u32) {
+        let (width, height) = (self.width, self.height);
+        let (tile_width, tile_height) = (self.tile_width, self.tile_height);
+        match self.orientation {
+            Orientation::Orthogonal => (width * tile_width, height * tile_height),
+            Orientation::Isometric => ((width + height) * tile_width / 2, (width + height) * tile_height / 2),
+            Orientation::Staggered | Orientation::Hexagonal => {
+                let side_length = match self.orientation {
+                    Orientation::Hexagonal => self.hex_side_length.unwrap_or(0),
+                    _ => 0,
+                };
+                match self.stagger_axis.unwrap_or(Axis::Y) {
+                    Axis::X => {
+                        let side_offset_x = tile_width.saturating_sub(side_length) / 2;
+                        let column_width = side_offset_x + side_length;
+                        let stagger = if width > 1 { tile_height / 2 } else { 0 };
+                        (width * column_width + side_offset_x, height * tile_height + stagger)
+                    }
+                    Axis::Y => {
+                        let side_offset_y = tile_height.saturating_sub(side_length) / 2;
+                        let row_height = side_offset_y + side_length;
+                        let stagger = if height > 1 { tile_width / 2 } else { 0 };
+                        (width * tile_width + stagger, height * row_height + side_offset_y)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the pixel position of the top-left tile of `chunk`, relative
+    /// to the position of the tile at (0, 0), taking the orientation and
+    /// the stagger settings of the map into account.
+    pub fn chunk_pixel_origin(&self, chunk: &Chunk) -> (i32, i32) {
+        let (x, y) = (chunk.x(), chunk.y());
+        let tile_width = self.tile_width as i32;
+        let tile_height = self.tile_height as i32;
+        match self.orientation {
+            Orientation::Orthogonal => (x * tile_width, y * tile_height),
+            Orientation::Isometric => ((x - y) * tile_width / 2, (x + y) * tile_height / 2),
+            Orientation::Staggered | Orientation::Hexagonal => {
+                let side_length = match self.orientation {
+                    Orientation::Hexagonal => self.hex_side_length.unwrap_or(0) as i32,
+                    _ => 0,
+                };
+                let index = self.stagger_index.unwrap_or(Index::Odd);
+                let is_staggered = |i: i32| match index {
+                    Index::Odd => i & 1 == 1,
+                    Index::Even => i & 1 == 0,
+                };
+                match self.stagger_axis.unwrap_or(Axis::Y) {
+                    Axis::Y => {
+                        let shift = if is_staggered(y) { tile_width / 2 } else { 0 };
+                        (x * tile_width + shift, y * (tile_height + side_length) / 2)
+                    }
+                    Axis::X => {
+                        let shift = if is_staggered(x) { tile_height / 2 } else { 0 };
+                        (x * (tile_width + side_length) / 2, y * tile_height + shift)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the corners of the diamond covered by the tile at `(x, y)`
+    /// on an isometric map, in pixels: top, right, bottom and left.
+    ///
+    /// The map is laid out like Tiled does, with the top corner of tile
+    /// (0, 0) horizontally offset so that no tile has negative coordinates.
+    /// The isometric projection is used whatever the map orientation.
+    pub fn iso_tile_polygon(&self, x: u32, y: u32) -> [(f32, f32); 4] {
+        let half_width = self.tile_width as f32 / 2.0;
+        let half_height = self.tile_height as f32 / 2.0;
+        let top_x = (self.height as f32 + x as f32 - y as f32) * half_width;
+        let top_y = (x + y) as f32 * half_height;
+        [(top_x, top_y),
+         (top_x + half_width, top_y + half_height),
+         (top_x, top_y + 2.0 * half_height),
+         (top_x - half_width, top_y + half_height)]
+    }
+
+    pub fn tileset_by_name(&self, name: &str) -> Option<&Tileset> {
+        self.tilesets.iter().find(|tileset| tileset.name() == name)
+    }
+
+    /// Finds the tileset owning the global tile id `gid` and returns it along
+    /// with the local id of the tile within it. Flip flags are ignored and
+    /// the empty tile (gid 0) resolves to `None`.
+    pub fn resolve_gid(&self, gid: u32) -> Option<(&Tileset, u32)> {
+        let gid = TileGid(gid).id();
+        if gid == 0 {
+            return None;
+        }
+        self.tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid() <= gid)
+            .max_by_key(|tileset| tileset.first_gid())
+            .map(|tileset| (tileset, gid - tileset.first_gid()))
+    }
+
+    /// Returns the index of the tile `gid` in a texture atlas holding the
+    /// tiles of all tilesets one after the other, in first gid order.
+    ///
+    /// Flip flags are ignored. Returns `None` for the empty tile and for
+    /// tiles beyond the declared tile count of their tileset.
+    pub fn atlas_index(&self, gid: u32) -> Option<usize> {
+        let (tileset, local_id) = self.resolve_gid(gid)?;
+        if local_id >= tileset.tile_count() {
+            return None;
+        }
+        let preceding: u32 = self.tilesets
+            .iter()
+            .filter(|other| other.first_gid() < tileset.first_gid())
+            .map(Tileset::tile_count)
+            .sum();
+        Some(preceding as usize + local_id as usize)
+    }
+
+    /// Returns the sum of the tile counts declared by all tilesets.
+    pub fn total_tile_count(&self) -> u32 {
+        self.tilesets.iter().map(Tileset::tile_count).sum()
+    }
+
     pub fn layers(&self) -> Layers {
         Layers(self.layers.iter())
     }
 
     fn add_layer(&mut self, layer: Layer) {
+        self.layer_order.push(LayerSlot::Layer(self.layers.len()));
         self.layers.push(layer);
     }
 
@@ -188,6 +508,7 @@ impl Map {
     }
 
     fn add_image_layer(&mut self, image_layer: ImageLayer) {
+        self.layer_order.push(LayerSlot::ImageLayer(self.image_layers.len()));
         self.image_layers.push(image_layer);
     }
 
@@ -195,17 +516,222 @@ impl Map {
         ObjectGroups(self.object_groups.iter())
     }
 
+    /// Returns the top-level object groups for editing, e.g. with
+    /// `ObjectGroup::translate`.
+    pub fn object_groups_mut(&mut self) -> ObjectGroupsMut {
+        ObjectGroupsMut(self.object_groups.iter_mut())
+    }
+
     fn add_object_group(&mut self, object_group: ObjectGroup) {
+        self.layer_order.push(LayerSlot::ObjectGroup(self.object_groups.len()));
         self.object_groups.push(object_group);
     }
+
+    /// Appends a tile layer on top of the existing layers. A layer without
+    /// an id is given the next layer id of the map.
+    pub fn push_layer(&mut self, mut layer: Layer) {
+        if layer.id() == 0 {
+            layer.set_id(self.allocate_layer_id());
+        }
+        self.add_layer(layer);
+    }
+
+    /// Appends an object group on top of the existing layers. A group
+    /// without an id is given the next layer id of the map.
+    pub fn push_object_group(&mut self, mut object_group: ObjectGroup) {
+        if object_group.id() == 0 {
+            object_group.set_id(self.allocate_layer_id());
+        }
+        self.add_object_group(object_group);
+    }
+
+    pub fn push_tileset(&mut self, tileset: Tileset) {
+        self.add_tileset(tileset);
+    }
+
+    pub fn groups(&self) -> Groups {
+        Groups(self.groups.iter())
+    }
+
+    fn add_group(&mut self, mut group: Group) {
+        group.resolve_offsets((0, 0));
+        self.layer_order.push(LayerSlot::Group(self.groups.len()));
+        self.groups.push(group);
+    }
+
+    /// Returns an iterator over the top-level layers of all kinds, in
+    /// document order.
+    pub fn all_layers(&self) -> LayerRefs {
+        LayerRefs {
+            layers: &self.layers,
+            image_layers: &self.image_layers,
+            object_groups: &self.object_groups,
+            groups: &self.groups,
+            order: self.layer_order.iter(),
+        }
+    }
+
+    /// Returns an iterator over the layers of all kinds, in document order,
+    /// each group being followed by its own layers.
+    pub fn all_layers_recursive(&self) -> NestedLayers {
+        NestedLayers {
+            stack: vec![self.all_layers()],
+            pending: None,
+        }
+    }
+
+    /// Returns the first layer of any kind named `name`, in document order,
+    /// looking into groups as well.
+    pub fn layer_by_name(&self, name: &str) -> Option<LayerRef> {
+        self.all_layers_recursive().find(|layer| layer.name() == name)
+    }
+
+    /// Returns the visible layers to draw, from bottom to top. Groups are
+    /// replaced by their visible children, and hidden groups are skipped
+    /// along with everything they contain.
+    pub fn render_list(&self) -> Vec<LayerRef> {
+        let mut list = Vec::new();
+        let mut layers = self.all_layers_recursive();
+        while let Some(layer) = layers.next() {
+            match layer {
+                _ if !layer.is_visible() => layers.skip_children(),
+                LayerRef::Group(_) => {}
+                _ => list.push(layer),
+            }
+        }
+        list
+    }
+
+    /// Returns the layers of any kind whose class is `class`, in document
+    /// order, looking into groups as well.
+    pub fn layers_of_class<'a, 'b>(&'a self, class: &'b str) -> LayersOfClass<'a, 'b> {
+        LayersOfClass {
+            layers: self.all_layers_recursive(),
+            class: class,
+        }
+    }
+
+    /// Returns the properties of the map, then of its tilesets and their
+    /// tiles, then of its layers and their objects in document order, each
+    /// along with its owner. Layers and objects within groups are included.
+    pub fn all_properties(&self) -> Vec<(PropertyOwner, &Property)> {
+        let mut properties: Vec<_> = self.properties.iter().map(|p| (PropertyOwner::Map, p)).collect();
+        for tileset in self.tilesets() {
+            properties.extend(tileset.properties().iter().map(|p| (PropertyOwner::Tileset(tileset), p)));
+            for tile in tileset.tiles() {
+                properties.extend(tile.properties().iter().map(|p| (PropertyOwner::Tile(tileset, tile), p)));
+            }
+        }
+        for layer in self.all_layers_recursive() {
+            properties.extend(layer.properties().iter().map(|p| (PropertyOwner::Layer(layer), p)));
+            if let LayerRef::ObjectGroup(object_group) = layer {
+                for object in object_group.objects() {
+                    properties.extend(object.properties().iter().map(|p| (PropertyOwner::Object(object), p)));
+                }
+            }
+        }
+        properties
+    }
+
+    /// Returns the first object id, other than 0, given to more than one
+    /// object of the map. Objects of tileset tiles are not considered.
+    pub(crate) fn duplicate_object_id(&self) -> Option<u32> {
+        let mut ids = HashSet::new();
+        for layer in self.all_layers_recursive() {
+            if let LayerRef::ObjectGroup(object_group) = layer {
+                for object in object_group.objects() {
+                    if object.id() != 0 && !ids.insert(object.id()) {
+                        return Some(object.id());
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the paths of the images used by the tilesets, their tiles and
+    /// the image layers, resolved against `base_dir` and without duplicates.
+    ///
+    /// Embedded images have no source and are left out.
+    pub fn image_sources<P: AsRef<Path>>(&self, base_dir: P) -> Vec<PathBuf> {
+        let mut images = Vec::new();
+        for tileset in &self.tilesets {
+            images.extend(tileset.image());
+            images.extend(tileset.tiles().filter_map(|tile| tile.image()));
+        }
+        for layer in self.all_layers_recursive() {
+            if let LayerRef::ImageLayer(image_layer) = layer {
+                images.extend(image_layer.image());
+            }
+        }
+
+        let mut sources = Vec::new();
+        for image in images {
+            if image.source().is_empty() {
+                continue;
+            }
+            let path = base_dir.as_ref().join(image.source());
+            if !sources.contains(&path) {
+                sources.push(path);
+            }
+        }
+        sources
+    }
+
+    /// Describes the map in a human-readable tree for inspection: its size
+    /// and orientation, then its tilesets and layers with their counts.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out,
+                         "Map {}x{} tiles of {}x{} px, {}",
+                         self.width,
+                         self.height,
+                         self.tile_width,
+                         self.tile_height,
+                         format!("{:?}", self.orientation).to_lowercase());
+        let _ = writeln!(out, "Tilesets: {}", self.tilesets.len());
+        for tileset in &self.tilesets {
+            let _ = writeln!(out,
+                             "  tileset \"{}\": first gid {}, {} tiles",
+                             tileset.name(),
+                             tileset.first_gid(),
+                             tileset.tile_count());
+        }
+        let _ = writeln!(out, "Layers: {}", self.layer_order.len());
+        let mut layers = self.all_layers_recursive();
+        while let Some(layer) = layers.next() {
+            summarize_layer(&mut out, layer, layers.depth() + 1);
+        }
+        out
+    }
+}
+
+fn summarize_layer(out: &mut String, layer: LayerRef, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let _ = match layer {
+        LayerRef::Layer(layer) => {
+            let tiles = layer.data().map_or(0, |data| data.tiles().count());
+            writeln!(out, "{}layer \"{}\": {} tiles", indent, layer.name(), tiles)
+        }
+        LayerRef::ImageLayer(image_layer) => {
+            writeln!(out, "{}image layer \"{}\"", indent, image_layer.name())
+        }
+        LayerRef::ObjectGroup(object_group) => {
+            writeln!(out,
+                     "{}object group \"{}\": {} objects",
+                     indent,
+                     object_group.name(),
+                     object_group.objects().count())
+        }
+        LayerRef::Group(group) => writeln!(out, "{}group \"{}\"", indent, group.name()),
+    };
 }
 
 impl FromStr for Map {
     type Err = Error;
 
     fn from_str(s: &str) -> ::Result<Map> {
-        let mut tmx = TmxReader::new(s.as_bytes());
-        tmx.read_map()
+        Map::from_reader(s.as_bytes())
     }
 }
 
@@ -303,7 +829,9 @@ impl FromStr for RenderOrder {
 
 #[derive(Debug)]
 pub struct Layer {
-    name: String,
+    id: u32,
+    name: Option<String>,
+    class: Option<String>,
     x: i32,
     y: i32,
     width: u32,
@@ -312,14 +840,17 @@ pub struct Layer {
     visible: bool,
     offset_x: i32,
     offset_y: i32,
-    properties: PropertyCollection,
+    appearance: LayerAppearance,
+    properties: Properties,
     data: Option<Data>,
 }
 
 impl Default for Layer {
     fn default() -> Layer {
         Layer {
-            name: String::default(),
+            id: 0,
+            name: None,
+            class: None,
             x: 0,
             y: 0,
             width: 0,
@@ -328,19 +859,346 @@ impl Default for Layer {
             visible: true,
             offset_x: 0,
             offset_y: 0,
-            properties: PropertyCollection::new(),
+            appearance: LayerAppearance::default(),
+            properties: Properties::new(),
             data: None,
         }
     }
 }
 
 impl Layer {
+    /// Returns the unique id of the layer, or 0 when none is given.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
+    pub fn name(&self) -> &str {
+        self.name_opt().unwrap_or("")
+    }
+
+    pub fn name_opt(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
+    fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = Some(name.into());
+    }
+
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_ref().map(String::as_str)
+    }
+
+    fn set_class<S: Into<String>>(&mut self, class: S) {
+        self.class = Some(class.into());
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    fn set_x(&mut self, x: i32) {
+        self.x = x;
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    fn set_y(&mut self, y: i32) {
+        self.y = y;
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn set_width(&mut self, width: u32) {
+        self.width = width;
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_height(&mut self, height: u32) {
+        self.height = height;
+    }
+
+    pub fn opacity(&self) -> Opacity {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: Opacity) {
+        self.opacity = opacity;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn offset_x(&self) -> i32 {
+        self.offset_x
+    }
+
+    fn set_offset_x(&mut self, offset_x: i32) {
+        self.offset_x = offset_x;
+    }
+
+    pub fn offset_y(&self) -> i32 {
+        self.offset_y
+    }
+
+    fn set_offset_y(&mut self, offset_y: i32) {
+        self.offset_y = offset_y;
+    }
+
+    pub fn parallax_x(&self) -> f64 {
+        self.appearance.parallax_x
+    }
+
+    pub fn parallax_y(&self) -> f64 {
+        self.appearance.parallax_y
+    }
+
+    pub fn tint_color(&self) -> Option<&Color> {
+        self.appearance.tint_color.as_ref()
+    }
+
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    fn set_properties(&mut self, properties: Properties) {
+        self.properties = properties;
+    }
+
+    /// Returns the property `name` of the layer, or else the property of
+    /// the same name of `map`, so that maps can give defaults for layers.
+    pub fn property_or_map<'a>(&'a self, name: &str, map: &'a Map) -> Option<&'a Property> {
+        self.properties.get(name).or_else(|| map.properties().get(name))
+    }
+
+    pub fn data(&self) -> Option<&Data> {
+        self.data.as_ref()
+    }
+
+    fn set_data(&mut self, data: Data) {
+        self.data = Some(data);
+    }
+
+    /// Returns the rectangle of tiles covered by the layer, as `(x, y,
+    /// width, height)` in tile coordinates.
+    ///
+    /// For infinite maps this is the smallest rectangle enclosing all the
+    /// chunks, which may start at negative coordinates.
+    pub fn bounds(&self) -> (i32, i32, i32, i32) {
+        let chunks = match self.data {
+            Some(ref data) if data.chunks().next().is_some() => data.chunks(),
+            _ => return (0, 0, self.width as i32, self.height as i32),
+        };
+        let (mut min_x, mut min_y) = (i32::MAX, i32::MAX);
+        let (mut max_x, mut max_y) = (i32::MIN, i32::MIN);
+        for chunk in chunks {
+            min_x = min_x.min(chunk.x());
+            min_y = min_y.min(chunk.y());
+            max_x = max_x.max(chunk.x() + chunk.width() as i32);
+            max_y = max_y.max(chunk.y() + chunk.height() as i32);
+        }
+        (min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    /// Returns the gid at `(x, y)` in tile coordinates, 0 where there is no
+    /// tile.
+    pub fn gid_at(&self, x: i32, y: i32) -> u32 {
+        let data = match self.data {
+            Some(ref data) => data,
+            None => return 0,
+        };
+        let tile = if data.chunks().next().is_some() {
+            data.chunks().filter_map(|chunk| chunk.tile_at(x, y)).next()
+        } else if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            data.tile(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        };
+        tile.map_or(0, |tile| tile.gid)
+    }
+
+    /// Returns an iterator over every cell within `bounds()`, row by row,
+    /// yielding `(x, y, gid)` with a gid of 0 for empty cells.
+    pub fn iter_tiles(&self) -> LayerTiles {
+        // Chunk tiles are gathered once rather than looked up per cell
+        let chunk_gids = match self.data {
+            Some(ref data) if data.chunks().next().is_some() => {
+                let mut gids = HashMap::new();
+                for (x, y, gid) in data.iter_global_tiles() {
+                    gids.entry((x, y)).or_insert(gid);
+                }
+                Some(gids)
+            }
+            _ => None,
+        };
+        LayerTiles {
+            layer: self,
+            bounds: self.bounds(),
+            chunk_gids: chunk_gids,
+            index: 0,
+        }
+    }
+
+    /// Returns the tiles of the layer as `height` rows of `width` columns.
+    ///
+    /// Cells not covered by the layer data hold the empty tile, gid 0.
+    /// Chunks of infinite maps are not included.
+    pub fn to_grid(&self) -> Vec<Vec<TileGid>> {
+        let mut grid = vec![vec![TileGid(0); self.width as usize]; self.height as usize];
+        if self.width == 0 {
+            return grid;
+        }
+        if let Some(ref data) = self.data {
+            let width = self.width as usize;
+            for (index, tile) in data.tiles().enumerate().take(width * self.height as usize) {
+                grid[index / width][index % width] = tile.tile_gid();
+            }
+        }
+        grid
+    }
+}
+
+/// Cursor over the cells of a layer, see `Layer::iter_tiles`.
+pub struct LayerTiles<'a> {
+    layer: &'a Layer,
+    bounds: (i32, i32, i32, i32),
+    chunk_gids: Option<HashMap<(i32, i32), u32>>,
+    index: i64,
+}
+
+impl<'a> Iterator for LayerTiles<'a> {
+    type Item = (i32, i32, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (x, y, width, height) = self.bounds;
+        if width <= 0 || self.index >= width as i64 * height as i64 {
+            return None;
+        }
+        let x = x + (self.index % width as i64) as i32;
+        let y = y + (self.index / width as i64) as i32;
+        self.index += 1;
+        let gid = match self.chunk_gids {
+            Some(ref gids) => gids.get(&(x, y)).cloned().unwrap_or(0),
+            None => self.layer.gid_at(x, y),
+        };
+        Some((x, y, gid))
+    }
+}
+
+#[derive(Debug)]
+pub struct ImageLayer {
+    id: u32,
+    name: Option<String>,
+    class: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    opacity: Opacity,
+    visible: bool,
+    offset_x: i32,
+    offset_y: i32,
+    repeat_x: bool,
+    repeat_y: bool,
+    appearance: LayerAppearance,
+    properties: Properties,
+    image: Option<Image>,
+}
+
+impl Default for ImageLayer {
+    fn default() -> ImageLayer {
+        ImageLayer {
+            id: 0,
+            name: None,
+            class: None,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            opacity: 1.0,
+            visible: true,
+            offset_x: 0,
+            offset_y: 0,
+            repeat_x: false,
+            repeat_y: false,
+            appearance: LayerAppearance::default(),
+            properties: Properties::new(),
+            image: None,
+        }
+    }
+}
+
+impl ImageLayer {
+    /// Returns the unique id of the layer, or 0 when none is given.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
     pub fn name(&self) -> &str {
-        &self.name
+        self.name_opt().unwrap_or("")
+    }
+
+    pub fn name_opt(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
     }
 
     fn set_name<S: Into<String>>(&mut self, name: S) {
-        self.name = name.into();
+        self.name = Some(name.into());
+    }
+
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_ref().map(String::as_str)
+    }
+
+    fn set_class<S: Into<String>>(&mut self, class: S) {
+        self.class = Some(class.into());
+    }
+
+    pub fn offset_x(&self) -> i32 {
+        self.offset_x
+    }
+
+    fn set_offset_x(&mut self, offset_x: i32) {
+        self.offset_x = offset_x;
+    }
+
+    pub fn offset_y(&self) -> i32 {
+        self.offset_y
+    }
+
+    fn set_offset_y(&mut self, offset_y: i32) {
+        self.offset_y = offset_y;
+    }
+
+    pub fn parallax_x(&self) -> f64 {
+        self.appearance.parallax_x
+    }
+
+    pub fn parallax_y(&self) -> f64 {
+        self.appearance.parallax_y
+    }
+
+    pub fn tint_color(&self) -> Option<&Color> {
+        self.appearance.tint_color.as_ref()
     }
 
     pub fn x(&self) -> i32 {
@@ -363,16 +1221,186 @@ impl Layer {
         self.width
     }
 
-    fn set_width(&mut self, width: u32) {
-        self.width = width;
+    fn set_width(&mut self, width: u32) {
+        self.width = width;
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_height(&mut self, height: u32) {
+        self.height = height;
+    }
+
+    pub fn opacity(&self) -> Opacity {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: Opacity) {
+        self.opacity = opacity;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    fn set_properties(&mut self, properties: Properties) {
+        self.properties = properties;
+    }
+
+    pub fn image(&self) -> Option<&Image> {
+        self.image.as_ref()
+    }
+
+    fn set_image(&mut self, image: Image) {
+        self.image = Some(image);
+    }
+
+    pub fn repeat_x(&self) -> bool {
+        self.repeat_x
+    }
+
+    fn set_repeat_x(&mut self, repeat_x: bool) {
+        self.repeat_x = repeat_x;
+    }
+
+    pub fn repeat_y(&self) -> bool {
+        self.repeat_y
+    }
+
+    fn set_repeat_y(&mut self, repeat_y: bool) {
+        self.repeat_y = repeat_y;
+    }
+}
+
+pub type Opacity = f64;
+
+/// Reads an opacity, clamping out-of-range values into `0.0..=1.0`.
+fn read_opacity(value: &str) -> ::Result<Opacity> {
+    let opacity: Opacity = try!(reader::read_num(value));
+    Ok(opacity.clamp(0.0, 1.0))
+}
+
+/// Parallax factors and tint color, which every kind of layer accepts.
+///
+/// A parallax factor of 1.0 means the layer scrolls with the map.
+#[derive(Debug)]
+struct LayerAppearance {
+    parallax_x: f64,
+    parallax_y: f64,
+    tint_color: Option<Color>,
+}
+
+impl Default for LayerAppearance {
+    fn default() -> LayerAppearance {
+        LayerAppearance {
+            parallax_x: 1.0,
+            parallax_y: 1.0,
+            tint_color: None,
+        }
+    }
+}
+
+impl LayerAppearance {
+    /// Reads the `parallaxx`, `parallaxy` and `tintcolor` attributes,
+    /// returning `false` for any other attribute.
+    fn read_attribute(&mut self, name: &str, value: &str) -> ::Result<bool> {
+        match name {
+            "parallaxx" => {
+                self.parallax_x = try!(reader::read_num(value));
+            }
+            "parallaxy" => {
+                self.parallax_y = try!(reader::read_num(value));
+            }
+            "tintcolor" => {
+                self.tint_color = Some(try!(Color::from_str(value)));
+            }
+            _ => {
+                return Ok(false);
+            }
+        };
+        Ok(true)
+    }
+}
+
+#[derive(Debug)]
+pub struct Group {
+    id: u32,
+    name: Option<String>,
+    class: Option<String>,
+    opacity: Opacity,
+    visible: bool,
+    offset_x: i32,
+    offset_y: i32,
+    absolute_offset: (i32, i32),
+    appearance: LayerAppearance,
+    properties: Properties,
+    layers: Vec<Layer>,
+    image_layers: Vec<ImageLayer>,
+    object_groups: Vec<ObjectGroup>,
+    groups: Vec<Group>,
+    layer_order: Vec<LayerSlot>,
+}
+
+impl Default for Group {
+    fn default() -> Group {
+        Group {
+            id: 0,
+            name: None,
+            class: None,
+            opacity: 1.0,
+            visible: true,
+            offset_x: 0,
+            offset_y: 0,
+            absolute_offset: (0, 0),
+            appearance: LayerAppearance::default(),
+            properties: Properties::new(),
+            layers: Vec::new(),
+            image_layers: Vec::new(),
+            object_groups: Vec::new(),
+            groups: Vec::new(),
+            layer_order: Vec::new(),
+        }
+    }
+}
+
+impl Group {
+    /// Returns the unique id of the group, or 0 when none is given.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
+    pub fn name(&self) -> &str {
+        self.name_opt().unwrap_or("")
+    }
+
+    pub fn name_opt(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
+    fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = Some(name.into());
     }
 
-    pub fn height(&self) -> u32 {
-        self.height
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_ref().map(String::as_str)
     }
 
-    fn set_height(&mut self, height: u32) {
-        self.height = height;
+    fn set_class<S: Into<String>>(&mut self, class: S) {
+        self.class = Some(class.into());
     }
 
     pub fn opacity(&self) -> Opacity {
@@ -407,151 +1435,243 @@ impl Layer {
         self.offset_y = offset_y;
     }
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+    pub fn parallax_x(&self) -> f64 {
+        self.appearance.parallax_x
     }
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
-        self.properties = properties;
+    pub fn parallax_y(&self) -> f64 {
+        self.appearance.parallax_y
     }
 
-    pub fn data(&self) -> Option<&Data> {
-        self.data.as_ref()
+    pub fn tint_color(&self) -> Option<&Color> {
+        self.appearance.tint_color.as_ref()
     }
 
-    fn set_data(&mut self, data: Data) {
-        self.data = Some(data);
+    /// Returns the offset of this group accumulated with the offsets of all
+    /// its ancestor groups.
+    pub fn absolute_offset(&self) -> (i32, i32) {
+        self.absolute_offset
     }
-}
-
-#[derive(Debug)]
-pub struct ImageLayer {
-    name: String,
-    x: i32,
-    y: i32,
-    width: u32,
-    height: u32,
-    opacity: Opacity,
-    visible: bool,
-    offset_x: i32,
-    offset_y: i32,
-    properties: PropertyCollection,
-    image: Option<Image>,
-}
 
-impl Default for ImageLayer {
-    fn default() -> ImageLayer {
-        ImageLayer {
-            name: String::default(),
-            x: 0,
-            y: 0,
-            width: 0,
-            height: 0,
-            opacity: 1.0,
-            visible: true,
-            offset_x: 0,
-            offset_y: 0,
-            properties: PropertyCollection::new(),
-            image: None,
-        }
+    /// Returns the effective offset of `layer`, a direct child of this group.
+    pub fn layer_offset(&self, layer: &Layer) -> (i32, i32) {
+        (self.absolute_offset.0 + layer.offset_x(), self.absolute_offset.1 + layer.offset_y())
     }
-}
 
-impl ImageLayer {
-    pub fn name(&self) -> &str {
-        &self.name
+    fn resolve_offsets(&mut self, parent_offset: (i32, i32)) {
+        self.absolute_offset = (parent_offset.0 + self.offset_x, parent_offset.1 + self.offset_y);
+        let absolute_offset = self.absolute_offset;
+        for group in &mut self.groups {
+            group.resolve_offsets(absolute_offset);
+        }
     }
 
-    fn set_name<S: Into<String>>(&mut self, name: S) {
-        self.name = name.into();
+    pub fn properties(&self) -> &Properties {
+        &self.properties
     }
 
-    pub fn offset_x(&self) -> i32 {
-        self.offset_x
+    fn set_properties(&mut self, properties: Properties) {
+        self.properties = properties;
     }
 
-    fn set_offset_x(&mut self, offset_x: i32) {
-        self.offset_x = offset_x;
+    pub fn layers(&self) -> Layers {
+        Layers(self.layers.iter())
     }
 
-    pub fn offset_y(&self) -> i32 {
-        self.offset_y
+    fn add_layer(&mut self, layer: Layer) {
+        self.layer_order.push(LayerSlot::Layer(self.layers.len()));
+        self.layers.push(layer);
     }
 
-    fn set_offset_y(&mut self, offset_y: i32) {
-        self.offset_y = offset_y;
+    pub fn image_layers(&self) -> ImageLayers {
+        ImageLayers(self.image_layers.iter())
     }
 
-    pub fn x(&self) -> i32 {
-        self.x
+    fn add_image_layer(&mut self, image_layer: ImageLayer) {
+        self.layer_order.push(LayerSlot::ImageLayer(self.image_layers.len()));
+        self.image_layers.push(image_layer);
     }
 
-    fn set_x(&mut self, x: i32) {
-        self.x = x;
+    pub fn object_groups(&self) -> ObjectGroups {
+        ObjectGroups(self.object_groups.iter())
     }
 
-    pub fn y(&self) -> i32 {
-        self.y
+    fn add_object_group(&mut self, object_group: ObjectGroup) {
+        self.layer_order.push(LayerSlot::ObjectGroup(self.object_groups.len()));
+        self.object_groups.push(object_group);
     }
 
-    fn set_y(&mut self, y: i32) {
-        self.y = y;
+    pub fn groups(&self) -> Groups {
+        Groups(self.groups.iter())
     }
 
-    pub fn width(&self) -> u32 {
-        self.width
+    fn add_group(&mut self, group: Group) {
+        self.layer_order.push(LayerSlot::Group(self.groups.len()));
+        self.groups.push(group);
     }
 
-    fn set_width(&mut self, width: u32) {
-        self.width = width;
+    /// Returns an iterator over the direct children of all kinds, in
+    /// document order.
+    pub fn all_layers(&self) -> LayerRefs {
+        LayerRefs {
+            layers: &self.layers,
+            image_layers: &self.image_layers,
+            object_groups: &self.object_groups,
+            groups: &self.groups,
+            order: self.layer_order.iter(),
+        }
     }
+}
 
-    pub fn height(&self) -> u32 {
-        self.height
-    }
+#[derive(Clone, Copy, Debug)]
+enum LayerSlot {
+    Layer(usize),
+    ImageLayer(usize),
+    ObjectGroup(usize),
+    Group(usize),
+}
 
-    fn set_height(&mut self, height: u32) {
-        self.height = height;
-    }
+/// A reference to a layer of any kind.
+#[derive(Clone, Copy, Debug)]
+pub enum LayerRef<'a> {
+    Layer(&'a Layer),
+    ImageLayer(&'a ImageLayer),
+    ObjectGroup(&'a ObjectGroup),
+    Group(&'a Group),
+}
 
-    pub fn opacity(&self) -> Opacity {
-        self.opacity
+impl<'a> LayerRef<'a> {
+    pub fn name(&self) -> &'a str {
+        match *self {
+            LayerRef::Layer(layer) => layer.name(),
+            LayerRef::ImageLayer(image_layer) => image_layer.name(),
+            LayerRef::ObjectGroup(object_group) => object_group.name(),
+            LayerRef::Group(group) => group.name(),
+        }
     }
 
-    fn set_opacity(&mut self, opacity: Opacity) {
-        self.opacity = opacity;
+    pub fn class(&self) -> Option<&'a str> {
+        match *self {
+            LayerRef::Layer(layer) => layer.class(),
+            LayerRef::ImageLayer(image_layer) => image_layer.class(),
+            LayerRef::ObjectGroup(object_group) => object_group.class(),
+            LayerRef::Group(group) => group.class(),
+        }
     }
 
     pub fn is_visible(&self) -> bool {
-        self.visible
+        match *self {
+            LayerRef::Layer(layer) => layer.is_visible(),
+            LayerRef::ImageLayer(image_layer) => image_layer.is_visible(),
+            LayerRef::ObjectGroup(object_group) => object_group.is_visible(),
+            LayerRef::Group(group) => group.is_visible(),
+        }
     }
 
-    fn set_visible(&mut self, visible: bool) {
-        self.visible = visible;
+    pub fn properties(&self) -> &'a Properties {
+        match *self {
+            LayerRef::Layer(layer) => layer.properties(),
+            LayerRef::ImageLayer(image_layer) => image_layer.properties(),
+            LayerRef::ObjectGroup(object_group) => object_group.properties(),
+            LayerRef::Group(group) => group.properties(),
+        }
     }
+}
+
+/// The element a property returned by `Map::all_properties` belongs to.
+#[derive(Clone, Copy, Debug)]
+pub enum PropertyOwner<'a> {
+    Map,
+    Layer(LayerRef<'a>),
+    Tileset(&'a Tileset),
+    Tile(&'a Tileset, &'a Tile),
+    Object(&'a Object),
+}
+
+pub struct LayerRefs<'a> {
+    layers: &'a [Layer],
+    image_layers: &'a [ImageLayer],
+    object_groups: &'a [ObjectGroup],
+    groups: &'a [Group],
+    order: ::std::slice::Iter<'a, LayerSlot>,
+}
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+impl<'a> Iterator for LayerRefs<'a> {
+    type Item = LayerRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.order.next().map(|slot| match *slot {
+            LayerSlot::Layer(i) => LayerRef::Layer(&self.layers[i]),
+            LayerSlot::ImageLayer(i) => LayerRef::ImageLayer(&self.image_layers[i]),
+            LayerSlot::ObjectGroup(i) => LayerRef::ObjectGroup(&self.object_groups[i]),
+            LayerSlot::Group(i) => LayerRef::Group(&self.groups[i]),
+        })
     }
+}
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
-        self.properties = properties;
+/// Depth-first cursor over the layers of a map and of its groups, see
+/// `Map::all_layers_recursive`.
+pub struct NestedLayers<'a> {
+    stack: Vec<LayerRefs<'a>>,
+    pending: Option<&'a Group>,
+}
+
+impl<'a> NestedLayers<'a> {
+    /// Returns the number of groups enclosing the last returned layer.
+    pub fn depth(&self) -> usize {
+        self.stack.len().saturating_sub(1)
     }
 
-    pub fn image(&self) -> Option<&Image> {
-        self.image.as_ref()
+    /// Skips the layers of the last returned group.
+    pub fn skip_children(&mut self) {
+        self.pending = None;
     }
+}
 
-    fn set_image(&mut self, image: Image) {
-        self.image = Some(image);
+impl<'a> Iterator for NestedLayers<'a> {
+    type Item = LayerRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(group) = self.pending.take() {
+            self.stack.push(group.all_layers());
+        }
+        while let Some(layers) = self.stack.last_mut() {
+            match layers.next() {
+                Some(layer) => {
+                    if let LayerRef::Group(group) = layer {
+                        self.pending = Some(group);
+                    }
+                    return Some(layer);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
     }
 }
 
-pub type Opacity = f64;
+/// Iterator over the layers of a given class, see `Map::layers_of_class`.
+pub struct LayersOfClass<'a, 'b> {
+    layers: NestedLayers<'a>,
+    class: &'b str,
+}
+
+impl<'a, 'b> Iterator for LayersOfClass<'a, 'b> {
+    type Item = LayerRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let class = self.class;
+        self.layers.find(|layer| layer.class() == Some(class))
+    }
+}
 
 #[derive(Debug)]
 pub struct ObjectGroup {
-    name: String,
+    id: u32,
+    name: Option<String>,
+    class: Option<String>,
     color: Option<Color>,
     x: i32,
     y: i32,
@@ -561,18 +1681,40 @@ pub struct ObjectGroup {
     visible: bool,
     offset_x: i32,
     offset_y: i32,
+    appearance: LayerAppearance,
     draw_order: DrawOrder,
-    properties: PropertyCollection,
+    properties: Properties,
     objects: Vec<Object>,
 }
 
 impl ObjectGroup {
+    /// Returns the unique id of the group, or 0 when none is given.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
     pub fn name(&self) -> &str {
-        &self.name
+        self.name_opt().unwrap_or("")
+    }
+
+    pub fn name_opt(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
     }
 
     fn set_name<S: Into<String>>(&mut self, name: S) {
-        self.name = name.into();
+        self.name = Some(name.into());
+    }
+
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_ref().map(String::as_str)
+    }
+
+    fn set_class<S: Into<String>>(&mut self, class: S) {
+        self.class = Some(class.into());
     }
 
     pub fn color(&self) -> Option<&Color> {
@@ -647,6 +1789,18 @@ impl ObjectGroup {
         self.offset_y = offset_y;
     }
 
+    pub fn parallax_x(&self) -> f64 {
+        self.appearance.parallax_x
+    }
+
+    pub fn parallax_y(&self) -> f64 {
+        self.appearance.parallax_y
+    }
+
+    pub fn tint_color(&self) -> Option<&Color> {
+        self.appearance.tint_color.as_ref()
+    }
+
     pub fn draw_order(&self) -> DrawOrder {
         self.draw_order
     }
@@ -655,11 +1809,11 @@ impl ObjectGroup {
         self.draw_order = draw_order;
     }
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+    pub fn properties(&self) -> &Properties {
+        &self.properties
     }
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
+    fn set_properties(&mut self, properties: Properties) {
         self.properties = properties;
     }
 
@@ -670,12 +1824,33 @@ impl ObjectGroup {
     fn add_object(&mut self, object: Object) {
         self.objects.push(object);
     }
+
+    /// Returns the objects in the order given by `draw_order()`: sorted by
+    /// id for `DrawOrder::Index`, in document order for `DrawOrder::TopDown`.
+    pub fn objects_in_draw_order(&self) -> Vec<&Object> {
+        let mut objects: Vec<_> = self.objects.iter().collect();
+        if self.draw_order == DrawOrder::Index {
+            objects.sort_by_key(|object| object.id());
+        }
+        objects
+    }
+
+    /// Moves every object of the group by `(dx, dy)` pixels. Polygon and
+    /// polyline points are relative to their object and move along with it.
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        for object in &mut self.objects {
+            object.x += dx as f64;
+            object.y += dy as f64;
+        }
+    }
 }
 
 impl Default for ObjectGroup {
     fn default() -> ObjectGroup {
         ObjectGroup {
-            name: String::default(),
+            id: 0,
+            name: None,
+            class: None,
             color: None,
             x: 0,
             y: 0,
@@ -685,8 +1860,9 @@ impl Default for ObjectGroup {
             visible: true,
             offset_x: 0,
             offset_y: 0,
+            appearance: LayerAppearance::default(),
             draw_order: DrawOrder::TopDown,
-            properties: PropertyCollection::new(),
+            properties: Properties::new(),
             objects: Vec::new(),
         }
     }
@@ -695,8 +1871,9 @@ impl Default for ObjectGroup {
 #[derive(Debug)]
 pub struct Object {
     id: u32,
-    name: String,
+    name: Option<String>,
     object_type: String,
+    class: Option<String>,
     x: f64,
     y: f64,
     width: f64,
@@ -704,7 +1881,7 @@ pub struct Object {
     rotation: f32,
     visible: bool,
     gid: Option<u32>,
-    properties: PropertyCollection,
+    properties: Properties,
     shape: Option<Shape>,
 }
 
@@ -712,8 +1889,9 @@ impl Default for Object {
     fn default() -> Object {
         Object {
             id: 0,
-            name: String::new(),
+            name: None,
             object_type: String::new(),
+            class: None,
             x: 0.0,
             y: 0.0,
             width: 0.0,
@@ -721,7 +1899,7 @@ impl Default for Object {
             rotation: 0.0,
             visible: true,
             gid: None,
-            properties: PropertyCollection::new(),
+            properties: Properties::new(),
             shape: None,
         }
     }
@@ -737,11 +1915,15 @@ impl Object {
     }
 
     pub fn name(&self) -> &str {
-        &self.name
+        self.name_opt().unwrap_or("")
+    }
+
+    pub fn name_opt(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
     }
 
     fn set_name<S: Into<String>>(&mut self, name: S) {
-        self.name = name.into();
+        self.name = Some(name.into());
     }
 
     pub fn object_type(&self) -> &str {
@@ -752,6 +1934,20 @@ impl Object {
         self.object_type = object_type.into();
     }
 
+    /// Returns the class of the object, read from the `class` attribute
+    /// (Tiled 1.9+) or else from the older `type` attribute.
+    pub fn class(&self) -> Option<&str> {
+        match self.class {
+            Some(ref class) => Some(class),
+            None if !self.object_type.is_empty() => Some(&self.object_type),
+            None => None,
+        }
+    }
+
+    fn set_class<S: Into<String>>(&mut self, class: S) {
+        self.class = Some(class.into());
+    }
+
     pub fn x(&self) -> f64 {
         self.x
     }
@@ -808,11 +2004,15 @@ impl Object {
         self.visible = visible;
     }
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.get(name)
     }
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
+    fn set_properties(&mut self, properties: Properties) {
         self.properties = properties;
     }
 
@@ -823,6 +2023,59 @@ impl Object {
     fn set_shape<S: Into<Shape>>(&mut self, shape: S) {
         self.shape = Some(shape.into());
     }
+
+    /// Returns the size of the object. Tile objects without an explicit size
+    /// take the size of their tile, as found in the tilesets of `map`.
+    pub fn effective_size(&self, map: &Map) -> (f32, f32) {
+        let (mut width, mut height) = (self.width as f32, self.height as f32);
+        if let Some((tileset, local_id)) = self.gid.and_then(|gid| map.resolve_gid(gid)) {
+            let (tile_width, tile_height) = tileset.tile(local_id)
+                .and_then(Tile::size)
+                .unwrap_or((tileset.tile_width(), tileset.tile_height()));
+            if width == 0.0 {
+                width = tile_width as f32;
+            }
+            if height == 0.0 {
+                height = tile_height as f32;
+            }
+        }
+        (width, height)
+    }
+
+    /// Returns the `(x, y)` coordinates of the map tiles overlapped by the
+    /// bounding box of the object, row by row, using the tile size of `map`.
+    ///
+    /// Tile objects are anchored at their bottom-left corner and default to
+    /// the size of their tile, see `effective_size`. Rotation is not taken
+    /// into account, and tiles outside the map are left out.
+    pub fn covered_tiles(&self, map: &Map) -> Vec<(u32, u32)> {
+        let (width, height) = self.effective_size(map);
+        let (width, height) = (width as f64, height as f64);
+        let top = if self.gid.is_some() { self.y - height } else { self.y };
+        let columns = covered_span(self.x, width, map.tile_width(), map.width());
+        let rows = covered_span(top, height, map.tile_height(), map.height());
+        let mut tiles = Vec::new();
+        for y in rows.0..rows.1 {
+            for x in columns.0..columns.1 {
+                tiles.push((x, y));
+            }
+        }
+        tiles
+    }
+}
+
+/// Returns the range of tiles, as `(first, end)`, overlapped by the pixel
+/// span starting at `start`, clipped to `0..count`.
+fn covered_span(start: f64, size: f64, tile_size: u32, count: u32) -> (u32, u32) {
+    if tile_size == 0 {
+        return (0, 0);
+    }
+    let tile_size = tile_size as f64;
+    let first = (start / tile_size).floor();
+    let end = if size > 0.0 { ((start + size) / tile_size).ceil() } else { first + 1.0 };
+    let first = first.max(0.0).min(count as f64);
+    let end = end.max(first).min(count as f64);
+    (first as u32, end as u32)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -837,7 +2090,8 @@ impl FromStr for DrawOrder {
     fn from_str(s: &str) -> ::Result<DrawOrder> {
         match s {
             "topdown" => Ok(DrawOrder::TopDown),
-            "index" => Ok(DrawOrder::Index),
+            // "manual" was written by very old versions of Tiled
+            "index" | "manual" => Ok(DrawOrder::Index),
             _ => Err(Error::BadDrawOrder(s.to_string())),
         }
     }
@@ -849,12 +2103,22 @@ impl<R: Read> ElementReader<Map> for TmxReader<R> {
             "version" => {
                 map.set_version(value);
             }
+            "class" => {
+                map.set_class(value);
+            }
             "orientation" => {
                 let orientation = try!(Orientation::from_str(value));
                 map.set_orientation(orientation);
             }
             "renderorder" => {
-                let render_order = try!(RenderOrder::from_str(value));
+                let render_order = match RenderOrder::from_str(value) {
+                    Err(err) if self.mode() == ReadMode::Lenient => {
+                        // Tolerate render orders from newer Tiled versions.
+                        self.warn(err);
+                        RenderOrder::RightDown
+                    }
+                    result => try!(result),
+                };
                 map.set_render_order(render_order);
             }
             "width" => {
@@ -893,6 +2157,24 @@ impl<R: Read> ElementReader<Map> for TmxReader<R> {
                 let next_object_id = try!(reader::read_num(value));
                 map.set_next_object_id(next_object_id);
             }
+            "nextlayerid" => {
+                let next_layer_id = try!(reader::read_num(value));
+                map.set_next_layer_id(next_layer_id);
+            }
+            "infinite" => {
+                let infinite = try!(reader::read_bool(value));
+                map.set_infinite(infinite);
+            }
+            "repeat" | "repeatx" | "repeaty" if self.mode() == ReadMode::Lenient => {
+                let repeat = try!(reader::read_bool(value));
+                if name != "repeaty" {
+                    map.set_repeat_x(repeat);
+                }
+                if name != "repeatx" {
+                    map.set_repeat_y(repeat);
+                }
+            }
+            _ if self.mode() == ReadMode::Lenient && self.is_custom_attribute(name) => {}
             _ => {
                 return Err(Error::UnknownAttribute(name.to_string()));
             }
@@ -900,6 +2182,13 @@ impl<R: Read> ElementReader<Map> for TmxReader<R> {
         Ok(())
     }
 
+    fn validate_attributes(&mut self, attributes: &[OwnedAttribute]) -> ::Result<()> {
+        if self.mode() == ReadMode::Strict {
+            try!(reader::require_attributes(attributes, &["width", "height", "tilewidth", "tileheight"]));
+        }
+        Ok(())
+    }
+
     fn read_children(&mut self, map: &mut Map, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
         match name {
             "properties" => {
@@ -907,7 +2196,8 @@ impl<R: Read> ElementReader<Map> for TmxReader<R> {
                 map.set_properties(properties);
             }
             "tileset" => {
-                let ts = try!(self.on_tileset(attributes));
+                let mut ts = try!(self.on_tileset(attributes));
+                ts.inherit_tile_size(map.tile_width(), map.tile_height());
                 map.add_tileset(ts);
             }
             "layer" => {
@@ -922,18 +2212,95 @@ impl<R: Read> ElementReader<Map> for TmxReader<R> {
                 let image_layer = try!(self.on_image_layer(attributes));
                 map.add_image_layer(image_layer);
             }
+            "group" => {
+                let group = try!(self.on_group(attributes));
+                map.add_group(group);
+            }
             _ => {}
         }
         Ok(())
     }
 }
 
+impl<R: Read> ElementReader<Group> for TmxReader<R> {
+    fn read_attributes(&mut self, group: &mut Group, name: &str, value: &str) -> ::Result<()> {
+        match name {
+            "id" => {
+                let id = try!(reader::read_num(value));
+                group.set_id(id);
+            }
+            "name" => {
+                group.set_name(value);
+            }
+            "class" => {
+                group.set_class(value);
+            }
+            "opacity" => {
+                let opacity = try!(read_opacity(value));
+                group.set_opacity(opacity);
+            }
+            "visible" => {
+                let visible = try!(reader::read_bool(value));
+                group.set_visible(visible);
+            }
+            "offsetx" => {
+                let offset_x = try!(reader::read_num(value));
+                group.set_offset_x(offset_x);
+            }
+            "offsety" => {
+                let offset_y = try!(reader::read_num(value));
+                group.set_offset_y(offset_y);
+            }
+            _ => {
+                if !try!(group.appearance.read_attribute(name, value)) {
+                    return Err(Error::UnknownAttribute(name.to_string()));
+                }
+            }
+        };
+        Ok(())
+    }
+
+    fn read_children(&mut self, group: &mut Group, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
+        match name {
+            "properties" => {
+                let properties = try!(self.on_properties(attributes));
+                group.set_properties(properties);
+            }
+            "layer" => {
+                let layer = try!(self.on_layer(attributes));
+                group.add_layer(layer);
+            }
+            "imagelayer" => {
+                let image_layer = try!(self.on_image_layer(attributes));
+                group.add_image_layer(image_layer);
+            }
+            "objectgroup" => {
+                let object_group = try!(self.on_object_group(attributes));
+                group.add_object_group(object_group);
+            }
+            "group" => {
+                let child = try!(self.on_group(attributes));
+                group.add_group(child);
+            }
+            _ => {}
+        };
+        Ok(())
+    }
+}
+
 impl<R: Read> ElementReader<Layer> for TmxReader<R> {
     fn read_attributes(&mut self, layer: &mut Layer, name: &str, value: &str) -> ::Result<()> {
         match name {
+            "id" => {
+                let id = try!(reader::read_num(value));
+                layer.set_id(id);
+            }
             "name" => {
                 layer.set_name(value);
             }
+            "class" => {
+                layer.set_class(value);
+            }
             "x" => {
                 let x = try!(reader::read_num(value));
                 layer.set_x(x);
@@ -951,14 +2318,12 @@ impl<R: Read> ElementReader<Layer> for TmxReader<R> {
                 layer.set_height(height);
             }
             "opacity" => {
-                let opacity = try!(reader::read_num(value));
+                let opacity = try!(read_opacity(value));
                 layer.set_opacity(opacity);
             }
             "visible" => {
-                let visibility = try!(reader::read_num::<u32>(value));
-                if visibility == 0 {
-                    layer.set_visible(false);
-                }
+                let visible = try!(reader::read_bool(value));
+                layer.set_visible(visible);
             }
             "offsetx" => {
                 let offset_x = try!(reader::read_num(value));
@@ -969,7 +2334,9 @@ impl<R: Read> ElementReader<Layer> for TmxReader<R> {
                 layer.set_offset_y(offset_y);
             }
             _ => {
-                return Err(Error::UnknownAttribute(name.to_string()));
+                if !try!(layer.appearance.read_attribute(name, value)) {
+                    return Err(Error::UnknownAttribute(name.to_string()));
+                }
             }
         };
         Ok(())
@@ -983,6 +2350,9 @@ impl<R: Read> ElementReader<Layer> for TmxReader<R> {
             }
             "data" => {
                 let data = try!(self.on_data(attributes));
+                if self.decode_tile_data() {
+                    try!(data.decode());
+                }
                 layer.set_data(data);
             }
             _ => {}
@@ -994,9 +2364,16 @@ impl<R: Read> ElementReader<Layer> for TmxReader<R> {
 impl<R: Read> ElementReader<ImageLayer> for TmxReader<R> {
     fn read_attributes(&mut self, image_layer: &mut ImageLayer, name: &str, value: &str) -> ::Result<()> {
         match name {
+            "id" => {
+                let id = try!(reader::read_num(value));
+                image_layer.set_id(id);
+            }
             "name" => {
                 image_layer.set_name(value);
             }
+            "class" => {
+                image_layer.set_class(value);
+            }
             "offsetx" => {
                 let offset_x = try!(reader::read_num(value));
                 image_layer.set_offset_x(offset_x);
@@ -1022,17 +2399,25 @@ impl<R: Read> ElementReader<ImageLayer> for TmxReader<R> {
                 image_layer.set_height(height);
             }
             "opacity" => {
-                let opacity = try!(reader::read_num(value));
+                let opacity = try!(read_opacity(value));
                 image_layer.set_opacity(opacity);
             }
             "visible" => {
-                let visibility = try!(reader::read_num::<u32>(value));
-                if visibility == 0 {
-                    image_layer.set_visible(false);
-                }
+                let visible = try!(reader::read_bool(value));
+                image_layer.set_visible(visible);
+            }
+            "repeatx" => {
+                let repeat_x = try!(reader::read_bool(value));
+                image_layer.set_repeat_x(repeat_x);
+            }
+            "repeaty" => {
+                let repeat_y = try!(reader::read_bool(value));
+                image_layer.set_repeat_y(repeat_y);
             }
             _ => {
-                return Err(Error::UnknownAttribute(name.to_string()));
+                if !try!(image_layer.appearance.read_attribute(name, value)) {
+                    return Err(Error::UnknownAttribute(name.to_string()));
+                }
             }
         };
         Ok(())
@@ -1045,8 +2430,13 @@ impl<R: Read> ElementReader<ImageLayer> for TmxReader<R> {
                 image_layer.set_properties(properties);
             }
             "image" => {
+                // Only the first image is kept; strict mode rejects the others.
                 let image = try!(self.on_image(attributes));
-                image_layer.set_image(image);
+                if image_layer.image().is_none() {
+                    image_layer.set_image(image);
+                } else if self.mode() == ReadMode::Strict {
+                    return Err(Error::DuplicateImage(image.source().to_string()));
+                }
             }
             _ => {}
         };
@@ -1057,9 +2447,16 @@ impl<R: Read> ElementReader<ImageLayer> for TmxReader<R> {
 impl<R: Read> ElementReader<ObjectGroup> for TmxReader<R> {
     fn read_attributes(&mut self, object_group: &mut ObjectGroup, name: &str, value: &str) -> ::Result<()> {
         match name {
+            "id" => {
+                let id = try!(reader::read_num(value));
+                object_group.set_id(id);
+            }
             "name" => {
                 object_group.set_name(value);
             }
+            "class" => {
+                object_group.set_class(value);
+            }
             "color" => {
                 let color = try!(Color::from_str(value));
                 object_group.set_color(color);
@@ -1081,14 +2478,12 @@ impl<R: Read> ElementReader<ObjectGroup> for TmxReader<R> {
                 object_group.set_height(height);
             }
             "opacity" => {
-                let opacity = try!(reader::read_num(value));
+                let opacity = try!(read_opacity(value));
                 object_group.set_opacity(opacity);
             }
             "visible" => {
-                let visibility = try!(reader::read_num::<u32>(value));
-                if visibility == 0 {
-                    object_group.set_visible(false);
-                }
+                let visible = try!(reader::read_bool(value));
+                object_group.set_visible(visible);
             }
             "offsetx" => {
                 let offset_x = try!(reader::read_num(value));
@@ -1103,7 +2498,9 @@ impl<R: Read> ElementReader<ObjectGroup> for TmxReader<R> {
                 object_group.set_draw_order(draw_order);
             }
             _ => {
-                return Err(Error::UnknownAttribute(name.to_string()));
+                if !try!(object_group.appearance.read_attribute(name, value)) {
+                    return Err(Error::UnknownAttribute(name.to_string()));
+                }
             }
         };
         Ok(())
@@ -1138,6 +2535,9 @@ impl<R: Read> ElementReader<Object> for TmxReader<R> {
             "type" => {
                 object.set_object_type(value);
             }
+            "class" => {
+                object.set_class(value);
+            }
             "x" => {
                 let x = try!(reader::read_num(value));
                 object.set_x(x);
@@ -1163,10 +2563,8 @@ impl<R: Read> ElementReader<Object> for TmxReader<R> {
                 object.set_gid(gid);
             }
             "visible" => {
-                let visibility = try!(reader::read_num::<u32>(value));
-                if visibility == 0 {
-                    object.set_visible(false);
-                }
+                let visible = try!(reader::read_bool(value));
+                object.set_visible(visible);
             }
             _ => {
                 return Err(Error::UnknownAttribute(name.to_string()));
@@ -1184,6 +2582,9 @@ impl<R: Read> ElementReader<Object> for TmxReader<R> {
             "ellipse" => {
                 object.set_shape(Shape::Ellipse);
             }
+            "point" => {
+                object.set_shape(Shape::Point);
+            }
             "polygon" => {
                 let polygon = try!(self.on_polygon(attributes));
                 object.set_shape(polygon);
@@ -1192,6 +2593,10 @@ impl<R: Read> ElementReader<Object> for TmxReader<R> {
                 let polyline = try!(self.on_polyline(attributes));
                 object.set_shape(polyline);
             }
+            "text" => {
+                let text = try!(self.on_text(attributes));
+                object.set_shape(text);
+            }
             _ => {}
         };
         Ok(())
@@ -23,20 +23,33 @@ macro_rules! define_iterator_wrapper {
             fn next(&mut self) -> Option<Self::Item> {
                 self.0.next()
             }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
         }
+
+        impl<'a> ExactSizeIterator for $name<'a> {}
     }
 }
 
 pub mod color;
 pub mod data;
+mod encoding;
+pub mod gid;
 pub mod image;
+pub mod loader;
 pub mod map;
 pub mod property;
 pub mod reader;
 pub mod shape;
 pub mod tileset;
 
-pub use self::map::Map;
+pub use self::color::Color;
+pub use self::gid::{flip_bits, strip_flags};
+pub use self::loader::{load, object_groups_from_reader, Loaded};
+pub use self::map::{ImageLayer, Layer, Map, Object, ObjectGroup, Orientation, RenderOrder};
+pub use self::property::Property;
 pub use self::tileset::Tileset;
 
 #[cfg(test)]
@@ -13,15 +13,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::io::Read;
 use std::str::FromStr;
 
 use error::Error;
 use model::reader::{TmxReader, ElementReader};
 
-define_iterator_wrapper!(Properties, Property);
+define_iterator_wrapper!(PropertyIter, Property);
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Property {
     name: String,
     value: String,
@@ -62,6 +63,18 @@ impl Property {
     fn set_value<S: Into<String>>(&mut self, value: S) {
         self.value = value.into();
     }
+
+    pub fn as_int(&self) -> Option<i32> {
+        self.value.parse().ok()
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        self.value.parse().ok()
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        self.value.parse().ok()
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -80,20 +93,78 @@ impl Default for PropertyType {
     }
 }
 
-#[derive(Debug, Default)]
-pub struct PropertyCollection(Vec<Property>);
+#[derive(Clone, Debug, Default)]
+pub struct Properties(Vec<Property>);
 
-impl PropertyCollection {
-    pub fn new() -> PropertyCollection {
-        PropertyCollection(Vec::new())
+impl Properties {
+    pub fn new() -> Properties {
+        Properties(Vec::new())
     }
 
     pub fn push(&mut self, property: Property) {
         self.0.push(property);
     }
 
-    pub fn iter(&self) -> Properties {
-        Properties(self.0.iter())
+    pub fn iter(&self) -> PropertyIter {
+        PropertyIter(self.0.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.0.iter().find(|p| p.name() == name)
+    }
+
+    /// Returns a new collection starting from `defaults`, where the
+    /// properties of `self` override the defaults sharing the same name.
+    pub fn merged_over(&self, defaults: &Properties) -> Properties {
+        let mut merged = Properties::new();
+        for default in defaults.iter() {
+            match self.get(default.name()) {
+                Some(property) => merged.push(property.clone()),
+                None => merged.push(default.clone()),
+            }
+        }
+        for property in self.iter() {
+            if defaults.get(property.name()).is_none() {
+                merged.push(property.clone());
+            }
+        }
+        merged
+    }
+}
+
+impl<'a> IntoIterator for &'a Properties {
+    type Item = &'a Property;
+    type IntoIter = PropertyIter<'a>;
+
+    fn into_iter(self) -> PropertyIter<'a> {
+        self.iter()
+    }
+}
+
+/// Maps class names (as defined in a Tiled project) to their default
+/// property values.
+#[derive(Debug, Default)]
+pub struct ClassRegistry(HashMap<String, Properties>);
+
+impl ClassRegistry {
+    pub fn new() -> ClassRegistry {
+        ClassRegistry(HashMap::new())
+    }
+
+    pub fn insert<S: Into<String>>(&mut self, class: S, defaults: Properties) {
+        self.0.insert(class.into(), defaults);
+    }
+
+    pub fn get(&self, class: &str) -> Option<&Properties> {
+        self.0.get(class)
     }
 }
 
@@ -13,18 +13,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::io::Read;
+use std::io::{self, BufRead, Cursor, Read};
 use std::str::FromStr;
 
 use xml::reader::{EventReader, XmlEvent};
 use xml::attribute::OwnedAttribute;
 
 use error::Error;
-use model::data::{Data, DataTile};
+use model::data::{Chunk, Data, DataTile};
 use model::image::Image;
-use model::map::{ImageLayer, Layer, Map, Object, ObjectGroup};
-use model::property::{PropertyCollection, Property};
-use model::shape::{Polygon, Polyline};
+use model::map::{Group, ImageLayer, Layer, Map, Object, ObjectGroup};
+use model::property::{Properties, Property};
+use model::shape::{Polygon, Polyline, Text};
 use model::tileset::{Animation, Terrain, TerrainCollection, Tile, TileOffset, Tileset, Frame};
 
 macro_rules! implement_handler {
@@ -34,8 +34,10 @@ macro_rules! implement_handler {
 
             // Process attributes
             for attr in attributes {
-                try!(<Self as ElementReader<$elem_type>>::read_attributes(self, &mut elem, &attr.name.local_name, &attr.value));
+                let result = <Self as ElementReader<$elem_type>>::read_attributes(self, &mut elem, &attr.name.local_name, &attr.value);
+                try!(self.recover(result));
             }
+            try!(<Self as ElementReader<$elem_type>>::validate_attributes(self, attributes));
 
             // Process children elements
             while let Ok(event) = self.reader.next() {
@@ -48,7 +50,7 @@ macro_rules! implement_handler {
                             break;
                         }
                     }
-                    XmlEvent::Characters(ref content) => {
+                    XmlEvent::Characters(ref content) | XmlEvent::CData(ref content) => {
                         try!(<Self as ElementReader<$elem_type>>::read_content(self, &mut elem, &content));
                     }
                     XmlEvent::EndDocument { .. } => {
@@ -63,30 +65,214 @@ macro_rules! implement_handler {
     }
 }
 
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
 pub fn read_num<T: FromStr>(s: &str) -> ::Result<T> {
     s.parse::<T>().map_err(|_| Error::InvalidNumber(s.to_string()))
 }
 
+/// Reads a boolean written either as `0`/`1` or as `false`/`true`.
+pub fn read_bool(s: &str) -> ::Result<bool> {
+    match s {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(Error::BadBool(s.to_string())),
+    }
+}
+
+/// Fails with `Error::MissingAttribute` unless all of `names` are present.
+pub fn require_attributes(attributes: &[OwnedAttribute], names: &[&str]) -> ::Result<()> {
+    for name in names {
+        if !attributes.iter().any(|attr| attr.name.local_name == *name) {
+            return Err(Error::MissingAttribute(name.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Consumes the UTF-8 byte order mark at the start of `source`, if any,
+/// since the XML parser does not accept it.
+pub fn skip_bom<R: BufRead>(source: &mut R) -> ::Result<()> {
+    let has_bom = try!(source.fill_buf()).starts_with(UTF8_BOM);
+    if has_bom {
+        source.consume(UTF8_BOM.len());
+    }
+    Ok(())
+}
+
+/// Source of UTF-8 input for the XML parser, see `utf8_source`.
+pub enum Utf8Source<R> {
+    Utf8(R),
+    Transcoded(Cursor<Vec<u8>>),
+}
+
+impl<R: Read> Read for Utf8Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Utf8Source::Utf8(ref mut source) => source.read(buf),
+            Utf8Source::Transcoded(ref mut source) => source.read(buf),
+        }
+    }
+}
+
+/// Prepares `source` for the XML parser, which only reads UTF-8.
+///
+/// UTF-16 input, recognized by its byte order mark or by its leading `<`,
+/// is transcoded to UTF-8 up front. A UTF-8 byte order mark is skipped.
+pub fn utf8_source<R: BufRead>(mut source: R) -> ::Result<Utf8Source<R>> {
+    let big_endian = {
+        let start = try!(source.fill_buf());
+        if start.starts_with(b"\xfe\xff") || start.starts_with(b"\0<") {
+            Some(true)
+        } else if start.starts_with(b"\xff\xfe") || start.starts_with(b"<\0") {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    let big_endian = match big_endian {
+        Some(big_endian) => big_endian,
+        None => {
+            try!(skip_bom(&mut source));
+            return Ok(Utf8Source::Utf8(source));
+        }
+    };
+
+    let mut bytes = Vec::new();
+    try!(source.read_to_end(&mut bytes));
+    if bytes.len() % 2 != 0 {
+        return Err(Error::Io(io::Error::new(io::ErrorKind::InvalidData, "truncated UTF-16 input")));
+    }
+    let units: Vec<u16> = bytes.chunks(2)
+        .map(|pair| if big_endian {
+            (pair[0] as u16) << 8 | pair[1] as u16
+        } else {
+            (pair[1] as u16) << 8 | pair[0] as u16
+        })
+        .collect();
+    let text = try!(String::from_utf16(&units)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)));
+    let text = text.trim_start_matches('\u{feff}');
+    Ok(Utf8Source::Transcoded(Cursor::new(text.as_bytes().to_vec())))
+}
+
+/// Controls how forgiving the reader is with nonconforming input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Tolerates nonstandard input where possible.
+    Lenient,
+    /// Rejects unknown attributes and values, but defaults missing ones.
+    Standard,
+    /// Like `Standard`, and additionally requires mandatory attributes.
+    Strict,
+}
+
+impl Default for ReadMode {
+    fn default() -> ReadMode {
+        ReadMode::Standard
+    }
+}
+
 pub struct TmxReader<R: Read> {
     reader: EventReader<R>,
+    mode: ReadMode,
+    collect_errors: bool,
+    decode_tile_data: bool,
+    custom_attribute_prefix: String,
+    errors: Vec<Error>,
 }
 
 impl<R: Read> TmxReader<R> {
 
     pub fn new(source: R) -> TmxReader<R> {
+        TmxReader::with_mode(source, ReadMode::default())
+    }
+
+    pub fn with_mode(source: R, mode: ReadMode) -> TmxReader<R> {
         TmxReader {
             reader: EventReader::new(source),
+            mode: mode,
+            collect_errors: false,
+            decode_tile_data: true,
+            custom_attribute_prefix: "x-".to_string(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> ReadMode {
+        self.mode
+    }
+
+    /// When enabled, invalid attributes are skipped and their errors are
+    /// collected instead of aborting the parse.
+    pub fn set_collect_errors(&mut self, collect_errors: bool) {
+        self.collect_errors = collect_errors;
+    }
+
+    pub fn decode_tile_data(&self) -> bool {
+        self.decode_tile_data
+    }
+
+    /// When disabled, layer data is left undecoded until its tiles are first
+    /// accessed, which saves time when only the map structure is needed.
+    ///
+    /// Tile data that cannot be decoded then only shows up as empty tiles
+    /// until `Data::decode()` is called; otherwise reading fails with the
+    /// error.
+    pub fn set_decode_tile_data(&mut self, decode_tile_data: bool) {
+        self.decode_tile_data = decode_tile_data;
+    }
+
+    pub fn custom_attribute_prefix(&self) -> &str {
+        &self.custom_attribute_prefix
+    }
+
+    /// Sets the prefix of the custom map attributes that are ignored in
+    /// lenient mode, `x-` by default. An empty prefix ignores none.
+    pub fn set_custom_attribute_prefix<S: Into<String>>(&mut self, prefix: S) {
+        self.custom_attribute_prefix = prefix.into();
+    }
+
+    /// Returns `true` if `name` starts with the custom attribute prefix.
+    pub fn is_custom_attribute(&self, name: &str) -> bool {
+        !self.custom_attribute_prefix.is_empty() && name.starts_with(self.custom_attribute_prefix.as_str())
+    }
+
+    /// Records a problem that was worked around rather than reported, so that
+    /// it is still returned by `take_errors`.
+    pub fn warn(&mut self, err: Error) {
+        self.errors.push(err);
+    }
+
+    /// Returns the errors collected so far, leaving none behind.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        ::std::mem::take(&mut self.errors)
+    }
+
+    fn recover(&mut self, result: ::Result<()>) -> ::Result<()> {
+        match result {
+            Err(err) if self.collect_errors => {
+                self.errors.push(err);
+                Ok(())
+            }
+            _ => result,
         }
     }
 
     pub fn read_map(&mut self) -> ::Result<Map> {
-        let mut result = Err(Error::BadXml);
         while let Ok(event) = self.reader.next() {
             match event {
                 XmlEvent::StartElement { ref name, ref attributes, .. } => {
                     if name.local_name == "map" {
-                        result = self.on_map(attributes);
+                        let map = try!(self.on_map(attributes));
+                        if self.mode == ReadMode::Strict {
+                            if let Some(id) = map.duplicate_object_id() {
+                                return Err(Error::DuplicateObjectId(id));
+                            }
+                        }
+                        return Ok(map);
                     }
+                    return Err(Error::BadXml(name.local_name.clone()));
                 }
                 XmlEvent::EndDocument { .. } => {
                     break;
@@ -94,7 +280,7 @@ impl<R: Read> TmxReader<R> {
                 _ => {}
             }
         }
-        result
+        Err(Error::BadXml(String::new()))
     }
 
     pub fn read_tileset(&mut self) -> ::Result<Tileset> {
@@ -102,8 +288,21 @@ impl<R: Read> TmxReader<R> {
             match event {
                 XmlEvent::StartElement { ref name, ref attributes, .. } => {
                     if name.local_name == "tileset" {
-                        return self.on_tileset(attributes);
+                        // Only a tileset reference in a map may point to another file
+                        if attributes.iter().any(|attr| attr.name.local_name == "source") {
+                            return Err(Error::UnexpectedAttribute("source".to_string()));
+                        }
+                        let tileset = try!(self.on_tileset(attributes));
+                        // There is no map to take the tile size from
+                        if tileset.tile_width() == 0 {
+                            return Err(Error::MissingAttribute("tilewidth".to_string()));
+                        }
+                        if tileset.tile_height() == 0 {
+                            return Err(Error::MissingAttribute("tileheight".to_string()));
+                        }
+                        return Ok(tileset);
                     }
+                    return Err(Error::BadXml(name.local_name.clone()));
                 }
                 XmlEvent::EndDocument { .. } => {
                     break;
@@ -111,7 +310,46 @@ impl<R: Read> TmxReader<R> {
                 _ => {}
             }
         }
-        Err(Error::BadXml)
+        Err(Error::BadXml(String::new()))
+    }
+
+    /// Reads the object groups of a map, including those nested in groups,
+    /// and skips everything else. Object groups attached to tileset tiles
+    /// are not returned.
+    pub fn read_object_groups(&mut self) -> ::Result<Vec<ObjectGroup>> {
+        let mut object_groups = Vec::new();
+        let mut in_map = false;
+        let mut tileset_depth = 0;
+        while let Ok(event) = self.reader.next() {
+            match event {
+                XmlEvent::StartElement { ref name, ref attributes, .. } => {
+                    match name.local_name.as_str() {
+                        "map" if !in_map => in_map = true,
+                        _ if !in_map => return Err(Error::BadXml(name.local_name.clone())),
+                        "tileset" => tileset_depth += 1,
+                        "objectgroup" if tileset_depth == 0 => {
+                            let object_group = try!(self.on_object_group(attributes));
+                            object_groups.push(object_group);
+                        }
+                        _ => {}
+                    }
+                }
+                XmlEvent::EndElement { ref name, .. } => {
+                    if name.local_name == "tileset" {
+                        tileset_depth -= 1;
+                    }
+                }
+                XmlEvent::EndDocument { .. } => {
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if in_map {
+            Ok(object_groups)
+        } else {
+            Err(Error::BadXml(String::new()))
+        }
     }
 
     implement_handler!(on_map, "map", Map);
@@ -119,12 +357,14 @@ impl<R: Read> TmxReader<R> {
     implement_handler!(on_layer, "layer", Layer);
     implement_handler!(on_image_layer, "imagelayer", ImageLayer);
     implement_handler!(on_object_group, "objectgroup", ObjectGroup);
+    implement_handler!(on_group, "group", Group);
     implement_handler!(on_object, "object", Object);
     implement_handler!(on_image, "image", Image);
     implement_handler!(on_tile_offset, "tileoffset", TileOffset);
-    implement_handler!(on_properties, "properties", PropertyCollection);
+    implement_handler!(on_properties, "properties", Properties);
     implement_handler!(on_data, "data", Data);
     implement_handler!(on_data_tile, "tile", DataTile);
+    implement_handler!(on_chunk, "chunk", Chunk);
     implement_handler!(on_terrain_types, "terraintypes", TerrainCollection);
     implement_handler!(on_tile, "tile", Tile);
     implement_handler!(on_property, "property", Property);
@@ -133,6 +373,7 @@ impl<R: Read> TmxReader<R> {
     implement_handler!(on_frame, "frame", Frame);
     implement_handler!(on_polygon, "polygon", Polygon);
     implement_handler!(on_polyline, "polyline", Polyline);
+    implement_handler!(on_text, "text", Text);
 }
 
 pub trait ElementReader<T> {
@@ -141,6 +382,11 @@ pub trait ElementReader<T> {
         Ok(())
     }
 
+    #[allow(unused_variables)]
+    fn validate_attributes(&mut self, attributes: &[OwnedAttribute]) -> ::Result<()> {
+        Ok(())
+    }
+
     #[allow(unused_variables)]
     fn read_children(&mut self, elem: &mut T, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()> {
         Ok(())
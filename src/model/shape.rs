@@ -17,6 +17,7 @@ use std::io::Read;
 use std::str::FromStr;
 
 use error::Error;
+use model::color::Color;
 use model::reader::{self, TmxReader, ElementReader};
 
 define_iterator_wrapper!(Points, Point);
@@ -24,8 +25,10 @@ define_iterator_wrapper!(Points, Point);
 #[derive(Debug, PartialEq, Eq)]
 pub enum Shape {
     Ellipse,
+    Point,
     Polygon(Polygon),
     Polyline(Polyline),
+    Text(Text),
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -70,6 +73,187 @@ impl From<Polyline> for Shape {
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct Text {
+    content: String,
+    font_family: String,
+    pixel_size: u32,
+    wrap: bool,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikeout: bool,
+    kerning: bool,
+    color: Color,
+    halign: HAlign,
+    valign: VAlign,
+}
+
+impl Default for Text {
+    fn default() -> Text {
+        Text {
+            content: String::new(),
+            font_family: "sans-serif".to_string(),
+            pixel_size: 16,
+            wrap: false,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikeout: false,
+            kerning: true,
+            color: Color(255, 0, 0, 0),
+            halign: HAlign::Left,
+            valign: VAlign::Top,
+        }
+    }
+}
+
+impl Text {
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    fn append_content(&mut self, content: &str) {
+        self.content.push_str(content);
+    }
+
+    pub fn font_family(&self) -> &str {
+        &self.font_family
+    }
+
+    fn set_font_family<S: Into<String>>(&mut self, font_family: S) {
+        self.font_family = font_family.into();
+    }
+
+    pub fn pixel_size(&self) -> u32 {
+        self.pixel_size
+    }
+
+    fn set_pixel_size(&mut self, pixel_size: u32) {
+        self.pixel_size = pixel_size;
+    }
+
+    pub fn wrap(&self) -> bool {
+        self.wrap
+    }
+
+    fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    pub fn bold(&self) -> bool {
+        self.bold
+    }
+
+    fn set_bold(&mut self, bold: bool) {
+        self.bold = bold;
+    }
+
+    pub fn italic(&self) -> bool {
+        self.italic
+    }
+
+    fn set_italic(&mut self, italic: bool) {
+        self.italic = italic;
+    }
+
+    pub fn underline(&self) -> bool {
+        self.underline
+    }
+
+    fn set_underline(&mut self, underline: bool) {
+        self.underline = underline;
+    }
+
+    pub fn strikeout(&self) -> bool {
+        self.strikeout
+    }
+
+    fn set_strikeout(&mut self, strikeout: bool) {
+        self.strikeout = strikeout;
+    }
+
+    pub fn kerning(&self) -> bool {
+        self.kerning
+    }
+
+    fn set_kerning(&mut self, kerning: bool) {
+        self.kerning = kerning;
+    }
+
+    pub fn color(&self) -> &Color {
+        &self.color
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    pub fn halign(&self) -> HAlign {
+        self.halign
+    }
+
+    fn set_halign(&mut self, halign: HAlign) {
+        self.halign = halign;
+    }
+
+    pub fn valign(&self) -> VAlign {
+        self.valign
+    }
+
+    fn set_valign(&mut self, valign: VAlign) {
+        self.valign = valign;
+    }
+}
+
+impl From<Text> for Shape {
+    fn from(text: Text) -> Shape {
+        Shape::Text(text)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HAlign {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl FromStr for HAlign {
+    type Err = Error;
+
+    fn from_str(s: &str) -> ::Result<HAlign> {
+        match s {
+            "left" => Ok(HAlign::Left),
+            "center" => Ok(HAlign::Center),
+            "right" => Ok(HAlign::Right),
+            "justify" => Ok(HAlign::Justify),
+            _ => Err(Error::BadAlignment(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VAlign {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl FromStr for VAlign {
+    type Err = Error;
+
+    fn from_str(s: &str) -> ::Result<VAlign> {
+        match s {
+            "top" => Ok(VAlign::Top),
+            "center" => Ok(VAlign::Center),
+            "bottom" => Ok(VAlign::Bottom),
+            _ => Err(Error::BadAlignment(s.to_string())),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Point {
     pub x: i32,
@@ -123,3 +307,62 @@ impl<R: Read> ElementReader<Polyline> for TmxReader<R> {
     }
 }
 
+
+impl<R: Read> ElementReader<Text> for TmxReader<R> {
+    fn read_attributes(&mut self, text: &mut Text, name: &str, value: &str) -> ::Result<()> {
+        match name {
+            "fontfamily" => {
+                text.set_font_family(value);
+            }
+            "pixelsize" => {
+                let pixel_size = try!(reader::read_num(value));
+                text.set_pixel_size(pixel_size);
+            }
+            "wrap" => {
+                let wrap = try!(reader::read_bool(value));
+                text.set_wrap(wrap);
+            }
+            "bold" => {
+                let bold = try!(reader::read_bool(value));
+                text.set_bold(bold);
+            }
+            "italic" => {
+                let italic = try!(reader::read_bool(value));
+                text.set_italic(italic);
+            }
+            "underline" => {
+                let underline = try!(reader::read_bool(value));
+                text.set_underline(underline);
+            }
+            "strikeout" => {
+                let strikeout = try!(reader::read_bool(value));
+                text.set_strikeout(strikeout);
+            }
+            "kerning" => {
+                let kerning = try!(reader::read_bool(value));
+                text.set_kerning(kerning);
+            }
+            "color" => {
+                let color = try!(Color::from_str(value));
+                text.set_color(color);
+            }
+            "halign" => {
+                let halign = try!(HAlign::from_str(value));
+                text.set_halign(halign);
+            }
+            "valign" => {
+                let valign = try!(VAlign::from_str(value));
+                text.set_valign(valign);
+            }
+            _ => {
+                return Err(Error::UnknownAttribute(name.to_string()));
+            }
+        };
+        Ok(())
+    }
+
+    fn read_content(&mut self, text: &mut Text, content: &str) -> ::Result<()> {
+        text.append_content(content);
+        Ok(())
+    }
+}
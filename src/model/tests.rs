@@ -13,12 +13,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use error::Error;
 use model::color::*;
+use model::gid::*;
+use model::loader::object_groups_from_reader;
 use model::map::*;
 use model::property::*;
+use model::reader::{ReadMode, TmxReader};
 use model::shape::*;
 use model::tileset::*;
 
@@ -28,6 +32,18 @@ fn after_reading_valid_xml_expect_map_to_have_version() {
     assert_eq!("1.0", map.version());
 }
 
+#[test]
+fn after_reading_valid_xml_expect_map_to_have_version_tuple() {
+    let map = get_simple_valid_map();
+    assert_eq!(Some((1, 0)), map.version_tuple());
+    let map = Map::from_str(r#"<map version="1.10"/>"#).unwrap();
+    assert_eq!(Some((1, 10)), map.version_tuple());
+    let map = Map::from_str(r#"<map version="1.x"/>"#).unwrap();
+    assert_eq!(None, map.version_tuple());
+    let map = Map::from_str("<map/>").unwrap();
+    assert_eq!(None, map.version_tuple());
+}
+
 #[test]
 fn after_reading_valid_xml_expect_map_to_have_orientation() {
     let map = get_simple_valid_map();
@@ -70,6 +86,359 @@ fn after_reading_valid_xml_expect_map_to_have_tile_height() {
     assert_eq!(32, map.tile_height());
 }
 
+#[test]
+fn after_reading_valid_xml_expect_map_to_have_dimensions() {
+    let map = get_simple_valid_map();
+    assert_eq!((200, 100), map.dimensions());
+    assert_eq!((16, 32), map.tile_dimensions());
+}
+
+#[test]
+fn after_reading_valid_xml_expect_map_to_have_pixel_size() {
+    let map = get_simple_valid_map();
+    assert_eq!((3200, 3200), map.pixel_size());
+
+    let map = Map::from_str(r#"<map orientation="isometric" width="10" height="6"
+        tilewidth="64" tileheight="32"/>"#).unwrap();
+    assert_eq!((512, 256), map.pixel_size());
+
+    let map = Map::from_str(r#"<map orientation="hexagonal" width="4" height="3"
+        tilewidth="32" tileheight="32" hexsidelength="16" staggeraxis="y"/>"#).unwrap();
+    assert_eq!((144, 80), map.pixel_size());
+}
+
+#[test]
+fn after_reading_infinite_map_expect_numeric_or_named_booleans() {
+    assert!(Map::from_str(r#"<map infinite="1"/>"#).unwrap().is_infinite());
+    assert!(Map::from_str(r#"<map infinite="true"/>"#).unwrap().is_infinite());
+    assert!(!Map::from_str(r#"<map infinite="false"/>"#).unwrap().is_infinite());
+    assert!(!Map::from_str("<map/>").unwrap().is_infinite());
+
+    let map = Map::from_str(r#"<map><layer visible="false"/><layer visible="true"/></map>"#).unwrap();
+    let visibility: Vec<_> = map.layers().map(Layer::is_visible).collect();
+    assert_eq!(vec![false, true], visibility);
+}
+
+#[test]
+fn when_creating_map_expect_orthogonal_and_empty() {
+    let map = Map::new(10, 8, 32, 16);
+    assert_eq!((10, 8), map.dimensions());
+    assert_eq!((32, 16), (map.tile_width(), map.tile_height()));
+    assert_eq!(Orientation::Orthogonal, map.orientation());
+    assert_eq!(RenderOrder::RightDown, map.render_order());
+    assert_eq!(1, map.next_object_id());
+    assert_eq!(0, map.tilesets().count());
+    assert_eq!(0, map.all_layers().count());
+}
+
+#[test]
+fn after_reading_compressed_embedded_image_expect_decoded_bytes() {
+    let map = Map::from_str(r#"<map>
+        <imagelayer>
+            <image format="gif">
+                <data encoding="base64" compression="zlib">eJxz93SzsEzULcisSM0pBgAbkQRr</data>
+            </image>
+        </imagelayer>
+        <imagelayer>
+            <image source="some_file.png"/>
+        </imagelayer>
+    </map>"#).unwrap();
+    let mut image_layers = map.image_layers();
+    let image = image_layers.next().unwrap().image().unwrap();
+    assert_eq!(Some(b"GIF89a-pixels".to_vec()), image.decoded_data().unwrap());
+    let image = image_layers.next().unwrap().image().unwrap();
+    assert_eq!(None, image.decoded_data().unwrap());
+}
+
+#[test]
+fn after_reading_map_properties_expect_typed_values() {
+    let map = Map::from_str(r#"<map>
+        <properties>
+            <property name="title" value="Caves"/>
+            <property name="level" type="int" value="3"/>
+            <property name="gravity" type="float" value="9.5"/>
+            <property name="dark" type="bool" value="true"/>
+        </properties>
+    </map>"#).unwrap();
+    assert_eq!(Some("Caves"), map.property_str("title"));
+    assert_eq!(Some(3), map.property_int("level"));
+    assert_eq!(Some(9.5), map.property_float("gravity"));
+    assert_eq!(Some(true), map.property_bool("dark"));
+    assert_eq!(None, map.property_int("title"));
+    assert_eq!(None, map.property_str("missing"));
+}
+
+#[test]
+fn after_reading_legacy_layer_position_in_lenient_mode_expect_it_to_be_kept() {
+    let map = Map::from_str_with_mode(r#"<map>
+        <layer name="old" x="0" y="0" width="2" height="1"/>
+        <layer name="moved" x="3" y="-1"/>
+    </map>"#, ReadMode::Lenient).unwrap();
+    let positions: Vec<_> = map.layers().map(|layer| (layer.x(), layer.y())).collect();
+    assert_eq!(vec![(0, 0), (3, -1)], positions);
+}
+
+#[test]
+fn after_reading_mixed_layers_expect_typed_iterators_to_know_their_length() {
+    let map = Map::from_str(r#"<map>
+        <layer/>
+        <objectgroup/>
+        <layer/>
+        <imagelayer/>
+        <layer/>
+    </map>"#).unwrap();
+    let object_groups = map.object_groups();
+    assert_eq!((1, Some(1)), object_groups.size_hint());
+    assert_eq!(3, map.layers().len());
+    assert_eq!(1, map.image_layers().len());
+    assert_eq!(5, map.all_layers().count());
+}
+
+#[test]
+fn after_reading_map_expect_render_list_of_visible_layers() {
+    let map = Map::from_str(r#"<map>
+        <layer name="ground"/>
+        <layer name="hidden" visible="0"/>
+        <group name="props">
+            <objectgroup name="items"/>
+            <imagelayer name="fog" visible="0"/>
+            <group name="nested">
+                <layer name="decals"/>
+            </group>
+        </group>
+        <group name="disabled" visible="0">
+            <layer name="debug"/>
+        </group>
+        <imagelayer name="sky"/>
+    </map>"#).unwrap();
+    let names: Vec<_> = map.render_list().iter().map(LayerRef::name).collect();
+    assert_eq!(vec!["ground", "items", "decals", "sky"], names);
+}
+
+#[test]
+fn after_reading_map_expect_nested_layers_in_document_order_with_depth() {
+    let map = Map::from_str(r#"<map>
+        <layer name="ground"/>
+        <group name="props">
+            <objectgroup name="items"/>
+            <group name="nested">
+                <layer name="decals"/>
+            </group>
+            <imagelayer name="fog"/>
+        </group>
+        <imagelayer name="sky"/>
+    </map>"#).unwrap();
+    let mut layers = map.all_layers_recursive();
+    let mut names = Vec::new();
+    while let Some(layer) = layers.next() {
+        names.push((layer.name(), layers.depth()));
+    }
+    assert_eq!(vec![("ground", 0), ("props", 0), ("items", 1), ("nested", 1), ("decals", 2), ("fog", 1), ("sky", 0)],
+               names);
+
+    let mut layers = map.all_layers_recursive();
+    layers.next();
+    layers.next();
+    layers.skip_children();
+    assert_eq!(Some("sky"), layers.next().map(|layer| layer.name()));
+}
+
+#[test]
+fn after_reading_map_expect_all_properties_with_their_owners() {
+    let map = Map::from_str(r#"<map>
+        <properties><property name="a" value="1"/></properties>
+        <tileset firstgid="1" tilewidth="8" tileheight="8">
+            <properties><property name="b" value="2"/></properties>
+            <tile id="0">
+                <properties><property name="c" value="3"/></properties>
+            </tile>
+        </tileset>
+        <layer name="ground">
+            <properties><property name="d" value="4"/></properties>
+        </layer>
+        <group>
+            <objectgroup>
+                <object id="1">
+                    <properties>
+                        <property name="e" value="5"/>
+                        <property name="f" value="6"/>
+                    </properties>
+                </object>
+            </objectgroup>
+        </group>
+    </map>"#).unwrap();
+    let properties = map.all_properties();
+    assert_eq!(6, properties.len());
+    let names: Vec<_> = properties.iter().map(|&(_, p)| p.name()).collect();
+    assert_eq!(vec!["a", "b", "c", "d", "e", "f"], names);
+    assert_matches!(properties[0].0, PropertyOwner::Map);
+    assert_matches!(properties[2].0, PropertyOwner::Tile(_, tile) if tile.id() == 0);
+    assert_matches!(properties[3].0, PropertyOwner::Layer(layer) if layer.name() == "ground");
+    assert_matches!(properties[5].0, PropertyOwner::Object(object) if object.id() == 1);
+}
+
+#[test]
+fn after_pushing_layers_expect_ids_from_next_layer_id() {
+    let mut map = Map::from_str(r#"<map nextlayerid="3">
+        <layer id="2" name="ground"/>
+    </map>"#).unwrap();
+    map.push_layer(Layer::default());
+    map.push_object_group(ObjectGroup::default());
+    map.push_tileset(Tileset::default());
+    let ids: Vec<_> = map.layers().map(Layer::id).collect();
+    assert_eq!(vec![2, 3], ids);
+    assert_eq!(4, map.object_groups().next().unwrap().id());
+    assert_eq!(5, map.next_layer_id());
+    assert_eq!(1, map.tilesets().count());
+    assert_eq!(3, map.all_layers().count());
+}
+
+#[test]
+fn after_reading_group_expect_offset_opacity_and_parallax() {
+    let map = Map::from_str(r##"<map>
+        <group id="7" offsetx="4" offsety="-2" opacity="0.5" visible="0" parallaxx="0.5" tintcolor="#ff0000"/>
+    </map>"##).unwrap();
+    let group = map.groups().next().unwrap();
+    assert_eq!(7, group.id());
+    assert_eq!((4, -2), (group.offset_x(), group.offset_y()));
+    assert_eq!(0.5, group.opacity());
+    assert!(!group.is_visible());
+    assert_eq!((0.5, 1.0), (group.parallax_x(), group.parallax_y()));
+    assert_eq!(Some(&Color(255, 255, 0, 0)), group.tint_color());
+}
+
+#[test]
+fn after_reading_boolean_attributes_expect_every_accepted_form() {
+    let map = Map::from_str_with_mode(r#"<map repeatx="true" repeaty="0">
+        <objectgroup>
+            <object visible="1">
+                <text wrap="true" bold="1" italic="false">Hi</text>
+            </object>
+            <object visible="false">
+                <text wrap="0" bold="false" italic="true">Hi</text>
+            </object>
+        </objectgroup>
+    </map>"#, ReadMode::Lenient).unwrap();
+    assert_eq!((true, false), map.repeat());
+    let mut objects = map.object_groups().next().unwrap().objects();
+    let object = objects.next().unwrap();
+    assert!(object.is_visible());
+    match object.shape() {
+        Some(&Shape::Text(ref text)) => assert_eq!((true, true, false), (text.wrap(), text.bold(), text.italic())),
+        other => panic!("unexpected shape: {:?}", other),
+    }
+    let object = objects.next().unwrap();
+    assert!(!object.is_visible());
+    match object.shape() {
+        Some(&Shape::Text(ref text)) => assert_eq!((false, false, true), (text.wrap(), text.bold(), text.italic())),
+        other => panic!("unexpected shape: {:?}", other),
+    }
+}
+
+#[test]
+fn after_reading_text_decorations_expect_kerning_and_strikeout() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object><text strikeout="1" kerning="0">Hi</text></object>
+            <object><text>Hi</text></object>
+        </objectgroup>
+    </map>"#).unwrap();
+    let mut objects = map.object_groups().next().unwrap().objects();
+    match objects.next().unwrap().shape() {
+        Some(&Shape::Text(ref text)) => {
+            assert!(text.strikeout());
+            assert!(!text.kerning());
+            assert!(!text.underline());
+        }
+        other => panic!("unexpected shape: {:?}", other),
+    }
+    match objects.next().unwrap().shape() {
+        Some(&Shape::Text(ref text)) => {
+            assert!(!text.strikeout());
+            assert!(text.kerning());
+        }
+        other => panic!("unexpected shape: {:?}", other),
+    }
+}
+
+#[test]
+fn when_reading_duplicate_object_ids_in_strict_mode_expect_error() {
+    let xml = r#"<map width="2" height="2" tilewidth="8" tileheight="8">
+        <objectgroup id="1">
+            <object id="3"/>
+        </objectgroup>
+        <group>
+            <objectgroup id="2">
+                <object id="4"/>
+                <object id="3"/>
+            </objectgroup>
+        </group>
+    </map>"#;
+    let result = Map::from_str_with_mode(xml, ReadMode::Strict);
+    assert_matches!(result, Err(Error::DuplicateObjectId(3)));
+    assert!(Map::from_str(xml).is_ok());
+}
+
+#[test]
+fn after_reading_tile_object_without_size_expect_tile_size() {
+    let map = Map::from_str(r#"<map tilewidth="16" tileheight="16">
+        <tileset firstgid="1" tilewidth="32" tileheight="24" tilecount="4"/>
+        <objectgroup>
+            <object gid="2" x="0" y="24"/>
+            <object gid="2" x="0" y="24" width="64" height="48"/>
+            <object x="0" y="0" width="10" height="5"/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let sizes: Vec<_> = map.object_groups().next().unwrap().objects().map(|o| o.effective_size(&map)).collect();
+    assert_eq!(vec![(32.0, 24.0), (64.0, 48.0), (10.0, 5.0)], sizes);
+}
+
+#[test]
+fn after_reading_point_object_expect_point_shape_without_size() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object id="1" x="12" y="34">
+                <point/>
+            </object>
+        </objectgroup>
+    </map>"#).unwrap();
+    let object = map.object_groups().next().unwrap().objects().next().unwrap();
+    assert_matches!(object.shape(), Some(&Shape::Point));
+    assert_eq!((12.0, 34.0), (object.x(), object.y()));
+    assert_eq!((0.0, 0.0), (object.width(), object.height()));
+}
+
+#[test]
+fn after_reading_hidden_object_expect_it_not_to_be_visible() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object id="1" visible="0"/>
+            <object id="2"/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let visibility: Vec<_> = map.object_groups().next().unwrap().objects().map(Object::is_visible).collect();
+    assert_eq!(vec![false, true], visibility);
+}
+
+#[test]
+fn when_reading_invalid_boolean_expect_bad_bool_error() {
+    let result = Map::from_str(r#"<map><layer visible="yes"/></map>"#);
+    assert_matches!(result, Err(Error::BadBool(ref value)) if value == "yes");
+}
+
+#[test]
+fn after_reading_map_without_orientation_expect_it_to_be_inferred() {
+    let map = Map::from_str(r#"<map hexsidelength="16" staggeraxis="x"/>"#).unwrap();
+    assert_eq!(Orientation::Orthogonal, map.orientation());
+    assert_eq!(Orientation::Hexagonal, map.infer_orientation());
+
+    let map = Map::from_str(r#"<map staggerindex="even"/>"#).unwrap();
+    assert_eq!(Orientation::Staggered, map.infer_orientation());
+
+    let map = Map::from_str(r#"<map orientation="isometric"/>"#).unwrap();
+    assert_eq!(Orientation::Isometric, map.infer_orientation());
+}
+
 #[test]
 fn after_reading_hexagonal_map_xml_expect_map_to_have_special_attributes() {
     let map = Map::from_str("<map/>").unwrap();
@@ -106,7 +475,37 @@ fn after_reading_valid_xml_with_properties_expect_map_to_have_properties() {
             <property name="prop4_name" value="true" type="bool"/>
         </properties>
     </map>"#).unwrap();
-    assert_eq!(4, map.properties().count());
+    assert_eq!(4, map.properties().len());
+    assert!(!map.properties().is_empty());
+
+    let mut names = Vec::new();
+    for property in map.properties() {
+        names.push(property.name());
+    }
+    assert_eq!(vec!["prop1_name", "prop2_name", "prop3_name", "prop4_name"], names);
+}
+
+#[test]
+fn expect_map_effective_properties_to_merge_class_defaults() {
+    let map = Map::from_str(r#"<map class="level">
+        <properties>
+            <property name="gravity" value="2" type="int"/>
+            <property name="title" value="Cave"/>
+        </properties>
+    </map>"#).unwrap();
+    assert_eq!(Some("level"), map.class());
+
+    let mut defaults = Properties::new();
+    defaults.push(Property::new("gravity", "1", PropertyType::Int));
+    defaults.push(Property::new("music", "theme.ogg", PropertyType::File));
+    let mut registry = ClassRegistry::new();
+    registry.insert("level", defaults);
+
+    let properties = map.effective_properties(&registry);
+    assert_eq!(3, properties.len());
+    assert_eq!("2", properties.get("gravity").unwrap().value());
+    assert_eq!("theme.ogg", properties.get("music").unwrap().value());
+    assert_eq!("Cave", properties.get("title").unwrap().value());
 }
 
 #[test]
@@ -139,10 +538,97 @@ fn when_reading_map_xml_with_invalid_render_order_expect_render_order_error() {
     assert_matches!(result, Err(Error::BadRenderOrder(..)));
 }
 
+#[test]
+fn after_reading_xml_with_bom_or_leading_whitespace_expect_valid_map() {
+    let map = Map::from_str("\u{feff}<?xml version=\"1.0\"?><map version=\"1.0\"/>").unwrap();
+    assert_eq!("1.0", map.version());
+    let map = Map::from_str("  \n<?xml version=\"1.0\"?><map version=\"1.0\"/>").unwrap();
+    assert_eq!("1.0", map.version());
+    let map = Map::from_reader("\u{feff}<map version=\"1.0\"/>".as_bytes()).unwrap();
+    assert_eq!("1.0", map.version());
+}
+
+#[test]
+fn after_reading_utf16_xml_without_bom_expect_valid_map() {
+    let xml = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><map version=\"1.0\"/>";
+    let bytes: Vec<u8> = xml.encode_utf16().flat_map(|unit| vec![(unit >> 8) as u8, unit as u8]).collect();
+    let map = Map::from_reader(&bytes[..]).unwrap();
+    assert_eq!("1.0", map.version());
+}
+
+#[test]
+fn when_reading_map_xml_without_tile_width_in_strict_mode_expect_missing_attribute_error() {
+    let xml = r#"<map width="10" height="10" tileheight="16"/>"#;
+    let result = Map::from_str_with_mode(xml, ReadMode::Strict);
+    assert_matches!(result, Err(Error::MissingAttribute(ref name)) if name == "tilewidth");
+
+    let map = Map::from_str_with_mode(xml, ReadMode::Lenient).unwrap();
+    assert_eq!(0, map.tile_width());
+}
+
+#[test]
+fn when_reading_map_with_repeat_attribute_expect_it_only_in_lenient_mode() {
+    let map = Map::from_str_with_mode(r#"<map repeat="1"/>"#, ReadMode::Lenient).unwrap();
+    assert_eq!((true, true), map.repeat());
+    let map = Map::from_str_with_mode(r#"<map repeatx="1"/>"#, ReadMode::Lenient).unwrap();
+    assert_eq!((true, false), map.repeat());
+    assert_eq!((false, false), Map::from_str("<map/>").unwrap().repeat());
+
+    let result = Map::from_str(r#"<map repeat="1"/>"#);
+    assert_matches!(result, Err(Error::UnknownAttribute(..)));
+}
+
+#[test]
+fn when_reading_unknown_render_order_in_lenient_mode_expect_default_and_warning() {
+    let xml = r#"<map renderorder="future-mode" width="1" height="1" tilewidth="1" tileheight="1"/>"#;
+    let mut reader = TmxReader::with_mode(xml.as_bytes(), ReadMode::Lenient);
+    let map = reader.read_map().unwrap();
+    assert_eq!(RenderOrder::RightDown, map.render_order());
+    let warnings = reader.take_errors();
+    assert_matches!(warnings[..], [Error::BadRenderOrder(ref value)] if value == "future-mode");
+
+    let result = Map::from_str_with_mode(xml, ReadMode::Strict);
+    assert_matches!(result, Err(Error::BadRenderOrder(..)));
+}
+
+#[test]
+fn when_reading_custom_map_attribute_expect_it_ignored_only_in_lenient_mode() {
+    let xml = r#"<map x-custom="hint" width="1" height="1" tilewidth="1" tileheight="1"/>"#;
+    let map = Map::from_str_with_mode(xml, ReadMode::Lenient).unwrap();
+    assert_eq!(1, map.width());
+
+    let result = Map::from_str_with_mode(xml, ReadMode::Strict);
+    assert_matches!(result, Err(Error::UnknownAttribute(ref name)) if name == "x-custom");
+
+    let xml = r#"<map ext-custom="hint"/>"#;
+    let mut reader = TmxReader::with_mode(xml.as_bytes(), ReadMode::Lenient);
+    reader.set_custom_attribute_prefix("ext-");
+    assert!(reader.read_map().is_ok());
+}
+
+#[test]
+fn when_collecting_errors_expect_all_recoverable_errors_to_be_reported() {
+    let (map, errors) = Map::from_str_collect_errors(r#"<map bad="" width="10">
+        <layer name="ground" opacity="0.5"/>
+        <objectgroup color="not-a-color"/>
+    </map>"#);
+    let map = map.unwrap();
+    assert_eq!(10, map.width());
+    assert_eq!(1, map.layers().count());
+    assert_eq!(1, map.object_groups().count());
+    assert_eq!(2, errors.len());
+    assert_matches!(errors[0], Error::UnknownAttribute(ref name) if name == "bad");
+    assert_matches!(errors[1], Error::InvalidColor(..));
+
+    let (map, errors) = Map::from_str_collect_errors("<nomap/>");
+    assert!(map.is_none());
+    assert_matches!(errors[..], [Error::BadXml(..)]);
+}
+
 #[test]
 fn when_reading_invalid_xml_element_expect_error() {
     let result = Map::from_str("<nomap/>");
-    assert_matches!(result, Err(Error::BadXml));
+    assert_matches!(result, Err(Error::BadXml(ref name)) if name == "nomap");
 }
 
 #[test]
@@ -151,12 +637,186 @@ fn when_reading_map_xml_with_invalid_background_color_expect_invalid_color_error
     assert_matches!(result, Err(Error::InvalidColor(..)));
 }
 
+#[test]
+fn after_reading_isometric_map_expect_tile_diamond_corners() {
+    let map = Map::from_str(r#"<map orientation="isometric" width="4" height="3" tilewidth="64" tileheight="32"/>"#).unwrap();
+    assert_eq!([(96.0, 0.0), (128.0, 16.0), (96.0, 32.0), (64.0, 16.0)], map.iso_tile_polygon(0, 0));
+    assert_eq!([(128.0, 48.0), (160.0, 64.0), (128.0, 80.0), (96.0, 64.0)], map.iso_tile_polygon(2, 1));
+    assert_eq!([(32.0, 32.0), (64.0, 48.0), (32.0, 64.0), (0.0, 48.0)], map.iso_tile_polygon(0, 2));
+}
+
+#[test]
+fn after_reading_layers_with_classes_expect_lookup_by_class() {
+    let map = Map::from_str(r#"<map>
+        <layer name="walls" class="collision"/>
+        <layer name="floor" class="ground"/>
+        <group name="upper" class="decoration">
+            <objectgroup name="barriers" class="collision"/>
+        </group>
+        <imagelayer name="sky"/>
+    </map>"#).unwrap();
+    let names: Vec<_> = map.layers_of_class("collision").map(|layer| layer.name()).collect();
+    assert_eq!(vec!["walls", "barriers"], names);
+    assert_eq!(Some("ground"), map.layers().nth(1).unwrap().class());
+    assert_eq!(0, map.layers_of_class("Collision").count());
+}
+
+#[test]
+fn after_reading_map_expect_summary_to_describe_its_contents() {
+    let map = Map::from_str(r#"<map orientation="isometric" width="2" height="2" tilewidth="32" tileheight="16">
+        <tileset firstgid="1" name="ground" tilecount="100"/>
+        <layer name="floor">
+            <data encoding="csv">1,2,3,4</data>
+        </layer>
+        <group name="upper">
+            <objectgroup name="walls">
+                <object/>
+                <object/>
+            </objectgroup>
+        </group>
+    </map>"#).unwrap();
+    let summary = map.summary();
+    assert!(summary.starts_with("Map 2x2 tiles of 32x16 px, isometric\n"));
+    assert!(summary.contains("tileset \"ground\": first gid 1, 100 tiles"));
+    assert!(summary.contains("Layers: 2"));
+    assert!(summary.contains("  layer \"floor\": 4 tiles"));
+    assert!(summary.contains("  group \"upper\"\n    object group \"walls\": 2 objects"));
+}
+
 #[test]
 fn after_reading_xml_with_tilesets_expect_map_to_be_iterable_over_tilesets() {
     let map = get_map_with_tilesets();
     assert_eq!(2, map.tilesets().count());
 }
 
+#[test]
+fn after_reading_xml_with_tilesets_expect_map_to_have_total_tile_count() {
+    let map = Map::from_str(r#"<map>
+        <tileset firstgid="1" tilecount="100"/>
+        <tileset firstgid="101" tilecount="50"/>
+    </map>"#).unwrap();
+    assert_eq!(150, map.total_tile_count());
+}
+
+#[test]
+fn after_reading_embedded_tileset_without_tile_size_expect_map_tile_size() {
+    let map = Map::from_str(r#"<map tilewidth="32" tileheight="16">
+        <tileset firstgid="1" name="inherits"/>
+        <tileset firstgid="10" name="own" tilewidth="8" tileheight="8"/>
+    </map>"#).unwrap();
+    let inherits = map.tileset_by_name("inherits").unwrap();
+    assert_eq!((32, 16), (inherits.tile_width(), inherits.tile_height()));
+    let own = map.tileset_by_name("own").unwrap();
+    assert_eq!((8, 8), (own.tile_width(), own.tile_height()));
+}
+
+#[test]
+fn after_reading_external_tileset_reference_expect_no_inherited_tile_size() {
+    let map = Map::from_str(r#"<map tilewidth="32" tileheight="16">
+        <tileset firstgid="1" source="other.tsx"/>
+    </map>"#).unwrap();
+    let reference = map.tilesets().next().unwrap();
+    assert_eq!((0, 0), (reference.tile_width(), reference.tile_height()));
+}
+
+#[test]
+fn when_reading_standalone_tileset_without_tile_size_expect_missing_attribute_error() {
+    let result = Tileset::from_str(r#"<tileset name="sizeless"/>"#);
+    assert_matches!(result, Err(Error::MissingAttribute(ref name)) if name == "tilewidth");
+    let result = Tileset::from_reader(r#"<tileset tilewidth="8"/>"#.as_bytes());
+    assert_matches!(result, Err(Error::MissingAttribute(ref name)) if name == "tileheight");
+}
+
+#[test]
+fn after_reading_map_expect_unique_image_sources_resolved_against_base_dir() {
+    let map = Map::from_str(r#"<map>
+        <tileset firstgid="1" name="ground">
+            <image source="tiles/ground.png"/>
+        </tileset>
+        <tileset firstgid="100" name="walls">
+            <image source="tiles/walls.png"/>
+        </tileset>
+        <imagelayer name="background">
+            <image source="tiles/ground.png"/>
+        </imagelayer>
+        <group>
+            <imagelayer name="sky">
+                <image source="sky.png"/>
+            </imagelayer>
+        </group>
+    </map>"#).unwrap();
+    let sources = map.image_sources("assets");
+    assert_eq!(vec![PathBuf::from("assets/tiles/ground.png"),
+                    PathBuf::from("assets/tiles/walls.png"),
+                    PathBuf::from("assets/sky.png")],
+               sources);
+}
+
+#[test]
+fn after_reading_xml_with_named_tilesets_expect_lookup_by_name() {
+    let map = Map::from_str(r#"<map>
+        <tileset firstgid="1" name="ground"/>
+        <tileset firstgid="101" name="walls"/>
+    </map>"#).unwrap();
+    assert_eq!(101, map.tileset_by_name("walls").unwrap().first_gid());
+    assert!(map.tileset_by_name("Walls").is_none());
+}
+
+#[test]
+fn after_reading_xml_with_tilesets_expect_gids_to_resolve_to_tileset_and_local_id() {
+    let map = Map::from_str(r#"<map>
+        <tileset firstgid="1" name="ground"/>
+        <tileset firstgid="101" name="walls"/>
+    </map>"#).unwrap();
+    let (tileset, local_id) = map.resolve_gid(1).unwrap();
+    assert_eq!("ground", tileset.name());
+    assert_eq!(0, local_id);
+    let (tileset, local_id) = map.resolve_gid(100).unwrap();
+    assert_eq!("ground", tileset.name());
+    assert_eq!(99, local_id);
+    let (tileset, local_id) = map.resolve_gid(0x8000_0000 | 105).unwrap();
+    assert_eq!("walls", tileset.name());
+    assert_eq!(4, local_id);
+    assert!(map.resolve_gid(0).is_none());
+}
+
+#[test]
+fn after_reading_xml_with_tilesets_expect_contiguous_atlas_indices() {
+    let map = Map::from_str(r#"<map>
+        <tileset firstgid="101" name="walls" tilecount="20"/>
+        <tileset firstgid="1" name="ground" tilecount="64"/>
+    </map>"#).unwrap();
+    assert_eq!(Some(0), map.atlas_index(1));
+    assert_eq!(Some(63), map.atlas_index(64));
+    assert_eq!(None, map.atlas_index(65));
+    assert_eq!(Some(64), map.atlas_index(101));
+    assert_eq!(Some(66), map.atlas_index(0x4000_0000 | 103));
+    assert_eq!(None, map.atlas_index(0));
+}
+
+#[test]
+fn after_reading_xml_with_self_closing_tileset_expect_following_siblings_to_be_read() {
+    let map = Map::from_str(r#"<map>
+        <tileset firstgid="1" source="ground.tsx"/>
+        <layer name="ground"/>
+        <tileset firstgid="10" name="nested">
+            <tile id="1"/>
+        </tileset>
+        <layer name="top"/>
+    </map>"#).unwrap();
+    assert_eq!(2, map.tilesets().count());
+    assert_eq!(2, map.layers().count());
+
+    let mut tilesets = map.tilesets();
+    assert_eq!("ground.tsx", tilesets.next().unwrap().source());
+    let nested = tilesets.next().unwrap();
+    assert_eq!(10, nested.first_gid());
+    assert_eq!(1, nested.tiles().count());
+
+    let names: Vec<_> = map.layers().map(Layer::name).collect();
+    assert_eq!(vec!["ground", "top"], names);
+}
+
 #[test]
 fn after_reading_xml_with_layers_expect_map_to_be_iterable_over_layers() {
     let map = get_map_with_layers();
@@ -187,7 +847,7 @@ fn after_reading_xml_with_layers_expect_map_to_be_iterable_over_layers() {
     assert_eq!(6, layer4.height());
 
     let layer5 = layers.next().unwrap();
-    assert_eq!(1, layer5.properties().count());
+    assert_eq!(1, layer5.properties().len());
 
     let layer6 = layers.next().unwrap();
     let data = layer6.data().unwrap();
@@ -203,6 +863,276 @@ fn after_reading_xml_with_layers_expect_map_to_be_iterable_over_layers() {
     assert_eq!(Some("SOME_ENCODED_AND_COMPRESSED_DATA"), data.raw_content());
 }
 
+#[test]
+fn after_reading_compressed_data_expect_tiles_to_hold_gids() {
+    let map = Map::from_str(r#"<map>
+        <layer>
+            <data encoding="base64" compression="gzip">H4sIAAAAAAACA2NkYGBgAmJmIAYAkyLgsAwAAAA=</data>
+        </layer>
+    </map>"#).unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    let gids: Vec<_> = data.tiles().map(|tile| tile.gid).collect();
+    assert_eq!(vec![1, 2, 3], gids);
+}
+
+#[test]
+fn when_reading_undecodable_data_expect_decode_error() {
+    let result = Map::from_str(r#"<map>
+        <layer>
+            <data encoding="base64" compression="lzma">AQAAAA==</data>
+        </layer>
+    </map>"#);
+    assert_matches!(result, Err(Error::BadCompression(ref compression)) if compression == "lzma");
+
+    let result = Map::from_str(r#"<map><layer><data encoding="base64">AQAAAAI=</data></layer></map>"#);
+    assert_matches!(result, Err(Error::BadTileData(..)));
+}
+
+#[test]
+fn after_reading_xml_encoded_data_expect_tiles_to_hold_gids() {
+    let map = Map::from_str(r#"<map>
+        <layer>
+            <data>
+                <tile gid="1"/>
+                <tile/>
+                <tile gid="3"/>
+                <tile gid="4"/>
+            </data>
+        </layer>
+    </map>"#).unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    assert_eq!(None, data.encoding());
+    let gids: Vec<_> = data.tiles().map(|tile| tile.gid).collect();
+    assert_eq!(vec![1, 0, 3, 4], gids);
+}
+
+#[test]
+fn when_tile_data_decoding_is_disabled_expect_structure_without_decoded_tiles() {
+    let xml = r#"<map width="2" height="2">
+        <tileset firstgid="1" name="ground"/>
+        <layer name="csv">
+            <data encoding="csv">1,2,3,4</data>
+        </layer>
+        <layer name="zlib">
+            <data encoding="base64" compression="zlib">eJxjZGBgYAJiZiAGAAA0AAc=</data>
+        </layer>
+    </map>"#;
+    let mut reader = TmxReader::new(xml.as_bytes());
+    reader.set_decode_tile_data(false);
+    let map = reader.read_map().unwrap();
+    assert_eq!(1, map.tilesets().count());
+    assert_eq!(2, map.layers().count());
+    for layer in map.layers() {
+        let data = layer.data().unwrap();
+        assert!(data.raw_content().is_some());
+        assert!(!data.is_decoded());
+    }
+
+    let map = Map::from_str(xml).unwrap();
+    let mut layers = map.layers();
+    let csv: Vec<_> = layers.next().unwrap().data().unwrap().tiles().map(|tile| tile.gid).collect();
+    assert_eq!(vec![1, 2, 3, 4], csv);
+    let zlib: Vec<_> = layers.next().unwrap().data().unwrap().tiles().map(|tile| tile.gid).collect();
+    assert_eq!(vec![1, 2, 3], zlib);
+}
+
+#[test]
+fn when_accessing_tiles_of_bad_data_before_decoding_expect_error_to_be_kept() {
+    let xml = r#"<map>
+        <layer>
+            <data encoding="base64">!!!!</data>
+        </layer>
+    </map>"#;
+    let mut reader = TmxReader::new(xml.as_bytes());
+    reader.set_decode_tile_data(false);
+    let map = reader.read_map().unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    assert_eq!(0, data.tiles().count());
+    assert!(!data.is_decoded());
+    assert_matches!(data.decode(), Err(Error::BadTileData(..)));
+    assert_eq!(0, data.tiles().count());
+    assert_matches!(data.decode(), Err(Error::BadTileData(..)));
+}
+
+#[test]
+fn when_accessing_undecoded_tiles_expect_decoding_only_once() {
+    let xml = r#"<map width="2" height="2">
+        <layer name="csv">
+            <data encoding="csv">1,2,3,4</data>
+        </layer>
+    </map>"#;
+    let mut reader = TmxReader::new(xml.as_bytes());
+    reader.set_decode_tile_data(false);
+    let map = reader.read_map().unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    assert!(!data.is_decoded());
+
+    let first = data.tiles().next().unwrap() as *const _;
+    assert!(data.is_decoded());
+    let second = data.tiles().next().unwrap() as *const _;
+    assert_eq!(first, second);
+    let gids: Vec<_> = data.tiles().map(|tile| tile.gid).collect();
+    assert_eq!(vec![1, 2, 3, 4], gids);
+}
+
+#[test]
+fn after_reading_layers_expect_properties_to_fall_back_to_map_properties() {
+    let map = Map::from_str(r#"<map>
+        <properties>
+            <property name="gravity" value="9.8" type="float"/>
+        </properties>
+        <layer name="water">
+            <properties>
+                <property name="gravity" value="2" type="float"/>
+            </properties>
+        </layer>
+        <layer name="ground"/>
+    </map>"#).unwrap();
+    let mut layers = map.layers();
+    let water = layers.next().unwrap();
+    assert_eq!("2", water.property_or_map("gravity", &map).unwrap().value());
+    let ground = layers.next().unwrap();
+    assert_eq!("9.8", ground.property_or_map("gravity", &map).unwrap().value());
+    assert!(ground.property_or_map("friction", &map).is_none());
+}
+
+#[test]
+fn after_reading_layer_expect_grid_of_rows_with_missing_cells_empty() {
+    let map = Map::from_str(r#"<map>
+        <layer width="3" height="2">
+            <data encoding="csv">1,2,3,4,2147483653</data>
+        </layer>
+    </map>"#).unwrap();
+    let grid = map.layers().next().unwrap().to_grid();
+    assert_eq!(2, grid.len());
+    assert!(grid.iter().all(|row| row.len() == 3));
+    assert_eq!(TileGid(3), grid[0][2]);
+    assert_eq!(5, grid[1][1].id());
+    assert!(grid[1][1].flipped_horizontally());
+    assert_eq!(TileGid(0), grid[1][2]);
+}
+
+#[test]
+fn after_reading_data_with_flipped_tile_expect_gid_to_keep_flags() {
+    let map = Map::from_str(r#"<map>
+        <layer>
+            <data>
+                <tile gid="2684354561"/>
+            </data>
+        </layer>
+    </map>"#).unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    let gid = data.tiles().next().unwrap().tile_gid();
+    assert_eq!(1, gid.id());
+    assert_eq!(Transform::Rotate90, gid.transform());
+}
+
+#[test]
+fn after_reading_xml_with_chunks_expect_global_tiles_to_be_contiguous() {
+    let map = Map::from_str(r#"<map>
+        <layer>
+            <data>
+                <chunk x="0" y="0" width="2" height="2">
+                    <tile gid="1"/>
+                    <tile gid="2"/>
+                    <tile gid="3"/>
+                    <tile gid="4"/>
+                </chunk>
+                <chunk x="2" y="0" width="2" height="2">
+                    <tile gid="5"/>
+                    <tile gid="6"/>
+                    <tile gid="7"/>
+                    <tile gid="8"/>
+                </chunk>
+            </data>
+        </layer>
+    </map>"#).unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    assert_eq!(2, data.chunks().count());
+
+    let tiles: Vec<_> = data.iter_global_tiles().collect();
+    assert_eq!(vec![(0, 0, 1), (1, 0, 2), (0, 1, 3), (1, 1, 4),
+                    (2, 0, 5), (3, 0, 6), (2, 1, 7), (3, 1, 8)], tiles);
+}
+
+#[test]
+fn after_reading_empty_chunk_expect_it_to_be_skipped() {
+    let map = Map::from_str(r#"<map>
+        <layer width="4" height="2">
+            <data encoding="csv">
+                <chunk x="0" y="0" width="2" height="2">0,0,0,0</chunk>
+                <chunk x="2" y="0" width="2" height="2">0,7,0,0</chunk>
+            </data>
+        </layer>
+    </map>"#).unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    assert_eq!(2, data.chunks().count());
+    let origins: Vec<_> = data.non_empty_chunks().map(|chunk| (chunk.x(), chunk.y())).collect();
+    assert_eq!(vec![(2, 0)], origins);
+}
+
+#[test]
+fn after_reading_chunks_at_negative_offsets_expect_layer_bounds_to_enclose_them() {
+    let map = Map::from_str(r#"<map>
+        <layer width="4" height="4">
+            <data encoding="csv">
+                <chunk x="-2" y="-1" width="2" height="1">1,2</chunk>
+                <chunk x="1" y="1" width="1" height="1">3</chunk>
+            </data>
+        </layer>
+        <layer width="2" height="1">
+            <data encoding="csv">4,5</data>
+        </layer>
+    </map>"#).unwrap();
+    let mut layers = map.layers();
+    let infinite = layers.next().unwrap();
+    assert_eq!((-2, -1, 4, 3), infinite.bounds());
+    let tiles: Vec<_> = infinite.iter_tiles().filter(|&(_, _, gid)| gid != 0).collect();
+    assert_eq!(vec![(-2, -1, 1), (-1, -1, 2), (1, 1, 3)], tiles);
+    assert_eq!(12, infinite.iter_tiles().count());
+
+    let finite = layers.next().unwrap();
+    assert_eq!((0, 0, 2, 1), finite.bounds());
+    let tiles: Vec<_> = finite.iter_tiles().collect();
+    assert_eq!(vec![(0, 0, 4), (1, 0, 5)], tiles);
+}
+
+#[test]
+fn after_reading_staggered_map_with_chunks_expect_chunk_pixel_origins() {
+    let map = Map::from_str(r#"<map orientation="staggered" tilewidth="64" tileheight="32"
+            staggeraxis="y" staggerindex="odd">
+        <layer>
+            <data>
+                <chunk x="0" y="1" width="16" height="16"/>
+                <chunk x="16" y="16" width="16" height="16"/>
+            </data>
+        </layer>
+    </map>"#).unwrap();
+    let data = map.layers().next().unwrap().data().unwrap();
+    let mut chunks = data.chunks();
+    assert_eq!((32, 16), map.chunk_pixel_origin(chunks.next().unwrap()));
+    assert_eq!((1024, 256), map.chunk_pixel_origin(chunks.next().unwrap()));
+}
+
+#[test]
+fn after_reading_xml_with_negative_offsets_expect_signed_values() {
+    let map = Map::from_str(r#"<map>
+        <layer offsetx="-5" offsety="-6"/>
+        <imagelayer offsetx="-7"/>
+        <objectgroup offsety="-8"/>
+        <tileset>
+            <tileoffset x="-1" y="-2"/>
+        </tileset>
+    </map>"#).unwrap();
+    let layer = map.layers().next().unwrap();
+    assert_eq!(-5, layer.offset_x());
+    assert_eq!(-6, layer.offset_y());
+    assert_eq!(-7, map.image_layers().next().unwrap().offset_x());
+    assert_eq!(-8, map.object_groups().next().unwrap().offset_y());
+    let offset = map.tilesets().next().unwrap().tile_offset().unwrap();
+    assert_eq!((-1, -2), (offset.x(), offset.y()));
+}
+
 #[test]
 fn after_reading_xml_with_image_layers_expect_map_to_be_iterable_over_image_layers() {
     let map = get_map_with_image_layers();
@@ -222,21 +1152,130 @@ fn after_reading_xml_with_image_layers_expect_map_to_be_iterable_over_image_laye
     let layer3 = layers.next().unwrap();
     assert!(!layer3.is_visible());
 
-    let layer4 = layers.next().unwrap();
-    assert_eq!(1, layer4.offset_x());
-    assert_eq!(2, layer4.offset_y());
+    let layer4 = layers.next().unwrap();
+    assert_eq!(1, layer4.offset_x());
+    assert_eq!(2, layer4.offset_y());
+
+    let layer5 = layers.next().unwrap();
+    assert_eq!(1, layer5.properties().len());
+
+    let layer6 = layers.next().unwrap();
+    assert!(layer6.image().is_some());
+
+    let layer7 = layers.next().unwrap();
+    assert_eq!(1, layer7.x());
+    assert_eq!(2, layer7.y());
+    assert_eq!(3, layer7.width());
+    assert_eq!(4, layer7.height());
+}
+
+#[test]
+fn after_reading_nameless_layer_expect_empty_name_and_no_name_opt() {
+    let map = Map::from_str(r#"<map>
+        <layer/>
+        <layer name=""/>
+        <objectgroup>
+            <object/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let mut layers = map.layers();
+    let nameless = layers.next().unwrap();
+    assert_eq!("", nameless.name());
+    assert_eq!(None, nameless.name_opt());
+    let empty = layers.next().unwrap();
+    assert_eq!("", empty.name());
+    assert_eq!(Some(""), empty.name_opt());
+
+    let object = map.object_groups().next().unwrap().objects().next().unwrap();
+    assert_eq!("", object.name());
+    assert_eq!(None, object.name_opt());
+}
+
+#[test]
+fn after_reading_fractional_opacity_expect_it_on_every_layer_kind() {
+    let map = Map::from_str(r#"<map>
+        <layer opacity="0.5"/>
+        <imagelayer opacity="0.5"/>
+        <objectgroup opacity="0.5"/>
+        <group opacity="0.5"/>
+    </map>"#).unwrap();
+    assert_eq!(0.5, map.layers().next().unwrap().opacity());
+    assert_eq!(0.5, map.image_layers().next().unwrap().opacity());
+    assert_eq!(0.5, map.object_groups().next().unwrap().opacity());
+    assert_eq!(0.5, map.groups().next().unwrap().opacity());
+}
+
+#[test]
+fn after_reading_image_layer_with_several_images_expect_first_one() {
+    let map = Map::from_str(r#"<map>
+        <imagelayer>
+            <image source="first.png"/>
+            <image source="second.png"/>
+        </imagelayer>
+    </map>"#).unwrap();
+    let image = map.image_layers().next().unwrap().image().unwrap();
+    assert_eq!("first.png", image.source());
+}
 
-    let layer5 = layers.next().unwrap();
-    assert_eq!(1, layer5.properties().count());
+#[test]
+fn when_reading_image_layer_with_several_images_in_strict_mode_expect_duplicate_image_error() {
+    let result = Map::from_str_with_mode(r#"<map width="1" height="1" tilewidth="1" tileheight="1">
+        <imagelayer>
+            <image source="first.png"/>
+            <image source="second.png"/>
+        </imagelayer>
+    </map>"#, ReadMode::Strict);
+    assert_matches!(result, Err(Error::DuplicateImage(ref source)) if source == "second.png");
+}
 
-    let layer6 = layers.next().unwrap();
-    assert!(layer6.image().is_some());
+#[test]
+fn after_reading_xml_with_nested_groups_expect_offsets_to_accumulate() {
+    let map = Map::from_str(r#"<map>
+        <group id="1" name="outer" offsetx="10" offsety="10">
+            <layer id="2" name="inner_layer" offsetx="5" offsety="5"/>
+            <group id="3" name="inner" offsetx="1" offsety="2">
+                <imagelayer id="4"/>
+            </group>
+        </group>
+    </map>"#).unwrap();
+    assert_eq!(1, map.groups().count());
+
+    let outer = map.groups().next().unwrap();
+    assert_eq!(1, outer.id());
+    assert_eq!("outer", outer.name());
+    assert_eq!((10, 10), outer.absolute_offset());
+    let layer = outer.layers().next().unwrap();
+    assert_eq!((15, 15), outer.layer_offset(layer));
+
+    let inner = outer.groups().next().unwrap();
+    assert_eq!(3, inner.id());
+    assert_eq!((11, 12), inner.absolute_offset());
+    assert_eq!(1, inner.image_layers().count());
+    assert_eq!(4, inner.image_layers().next().unwrap().id());
+}
 
-    let layer7 = layers.next().unwrap();
-    assert_eq!(1, layer7.x());
-    assert_eq!(2, layer7.y());
-    assert_eq!(3, layer7.width());
-    assert_eq!(4, layer7.height());
+#[test]
+fn after_reading_xml_with_layers_of_all_kinds_expect_lookup_by_name() {
+    let map = Map::from_str(r#"<map>
+        <layer name="ground"/>
+        <objectgroup name="objects"/>
+        <group name="group">
+            <imagelayer name="sky"/>
+        </group>
+        <imagelayer name="sky"/>
+    </map>"#).unwrap();
+    let names: Vec<_> = map.all_layers().map(|layer| layer.name()).collect();
+    assert_eq!(vec!["ground", "objects", "group", "sky"], names);
+
+    assert_matches!(map.layer_by_name("objects"), Some(LayerRef::ObjectGroup(..)));
+    match map.layer_by_name("sky") {
+        Some(LayerRef::ImageLayer(layer)) => {
+            let group = map.groups().next().unwrap();
+            assert!(::std::ptr::eq(layer, group.image_layers().next().unwrap()));
+        }
+        other => panic!("unexpected layer: {:?}", other),
+    }
+    assert!(map.layer_by_name("missing").is_none());
 }
 
 #[test]
@@ -256,7 +1295,7 @@ fn after_reading_xml_with_object_groups_expect_map_to_be_iterable_over_object_gr
     assert_eq!(0, group1.width());
     assert_eq!(0, group1.height());
     assert_eq!(DrawOrder::TopDown, group1.draw_order());
-    assert_eq!(1, group1.properties().count());
+    assert_eq!(1, group1.properties().len());
 
     let group2 = object_groups.next().unwrap();
     assert_eq!(0.0, group2.opacity());
@@ -274,6 +1313,207 @@ fn after_reading_xml_with_object_groups_expect_map_to_be_iterable_over_object_gr
     assert_eq!(Some(&Color(255, 0, 0, 0)), group3.color());
 }
 
+#[test]
+fn after_reading_object_group_with_parallax_and_tint_expect_them_to_be_read() {
+    let map = Map::from_str(r##"<map>
+        <objectgroup parallaxx="0.5" parallaxy="2" tintcolor="#ff8000" opacity="1.5" offsetx="-4" offsety="-8"/>
+        <objectgroup opacity="-0.5"/>
+    </map>"##).unwrap();
+    let mut object_groups = map.object_groups();
+    let group = object_groups.next().unwrap();
+    assert_eq!(0.5, group.parallax_x());
+    assert_eq!(2.0, group.parallax_y());
+    assert_eq!(Some(&Color(255, 255, 128, 0)), group.tint_color());
+    assert_eq!(1.0, group.opacity());
+    assert_eq!((-4, -8), (group.offset_x(), group.offset_y()));
+
+    let group = object_groups.next().unwrap();
+    assert_eq!(0.0, group.opacity());
+    assert_eq!(1.0, group.parallax_x());
+    assert_eq!(None, group.tint_color());
+}
+
+#[test]
+fn after_reading_tile_and_image_layers_with_parallax_and_tint_expect_them_to_be_read() {
+    let map = Map::from_str(r##"<map>
+        <layer parallaxx="0.5" tintcolor="#ff0000"/>
+        <imagelayer parallaxy="0.25" tintcolor="#80ff0000"/>
+    </map>"##).unwrap();
+    let layer = map.layers().next().unwrap();
+    assert_eq!((0.5, 1.0), (layer.parallax_x(), layer.parallax_y()));
+    assert_eq!(Some(&Color(255, 255, 0, 0)), layer.tint_color());
+
+    let image_layer = map.image_layers().next().unwrap();
+    assert_eq!((1.0, 0.25), (image_layer.parallax_x(), image_layer.parallax_y()));
+    assert_eq!(Some(&Color(128, 255, 0, 0)), image_layer.tint_color());
+}
+
+#[test]
+fn after_reading_image_layers_with_repeat_expect_repeat_flags() {
+    let map = Map::from_str(r#"<map>
+        <imagelayer repeatx="1"/>
+        <imagelayer repeaty="true"/>
+        <imagelayer/>
+    </map>"#).unwrap();
+    let flags: Vec<_> = map.image_layers().map(|layer| (layer.repeat_x(), layer.repeat_y())).collect();
+    assert_eq!(vec![(true, false), (false, true), (false, false)], flags);
+
+    let result = Map::from_str(r#"<map><imagelayer repeatx="yes"/></map>"#);
+    assert_matches!(result, Err(Error::BadBool(ref value)) if value == "yes");
+}
+
+#[test]
+fn after_reading_tile_and_shape_objects_expect_gid_only_on_tile_objects() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object gid="7" x="0" y="16" width="16" height="16"/>
+            <object x="0" y="0" width="32" height="8"/>
+            <object gid="0"/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let mut objects = map.object_groups().next().unwrap().objects();
+    assert_eq!(Some(7), objects.next().unwrap().gid());
+    assert_eq!(None, objects.next().unwrap().gid());
+    assert_eq!(Some(0), objects.next().unwrap().gid());
+}
+
+#[test]
+fn after_reading_objects_with_negative_or_scientific_coordinates_expect_them_parsed() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object x="-12.5" y="1e2" width="2.5E-1"/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let object = map.object_groups().next().unwrap().objects().next().unwrap();
+    assert_eq!(-12.5, object.x());
+    assert_eq!(100.0, object.y());
+    assert_eq!(0.25, object.width());
+}
+
+#[test]
+fn when_reading_object_with_invalid_coordinate_expect_invalid_number_error() {
+    let result = Map::from_str(r#"<map>
+        <objectgroup>
+            <object x="12px"/>
+        </objectgroup>
+    </map>"#);
+    assert_matches!(result, Err(Error::InvalidNumber(ref number)) if number == "12px");
+}
+
+#[test]
+fn after_reading_objects_expect_covered_tiles_from_their_bounding_box() {
+    let map = Map::from_str(r#"<map width="10" height="10" tilewidth="16" tileheight="16">
+        <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" tilecount="4" columns="2"/>
+        <objectgroup>
+            <object x="8" y="16" width="32" height="20"/>
+            <object x="20" y="20"/>
+            <object x="-8" y="150" width="16" height="16"/>
+            <object gid="1" x="16" y="32" width="16" height="16"/>
+            <object gid="2" x="16" y="32"/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let mut objects = map.object_groups().next().unwrap().objects();
+    let rectangle = objects.next().unwrap();
+    assert_eq!(vec![(0, 1), (1, 1), (2, 1), (0, 2), (1, 2), (2, 2)], rectangle.covered_tiles(&map));
+    let point = objects.next().unwrap();
+    assert_eq!(vec![(1, 1)], point.covered_tiles(&map));
+    let clipped = objects.next().unwrap();
+    assert_eq!(vec![(0, 9)], clipped.covered_tiles(&map));
+    let tile_object = objects.next().unwrap();
+    assert_eq!(vec![(1, 1)], tile_object.covered_tiles(&map));
+    let unsized_tile_object = objects.next().unwrap();
+    assert_eq!(vec![(1, 1)], unsized_tile_object.covered_tiles(&map));
+}
+
+#[test]
+fn after_translating_object_group_expect_objects_to_move() {
+    let mut map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object id="1" x="10" y="20"/>
+            <object id="2" x="-5" y="0.5"><polygon points="0,0 4,4 0,4"/></object>
+        </objectgroup>
+    </map>"#).unwrap();
+    map.object_groups_mut().next().unwrap().translate(2.0, -1.5);
+    let object_group = map.object_groups().next().unwrap();
+    let positions: Vec<_> = object_group.objects().map(|o| (o.x(), o.y())).collect();
+    assert_eq!(vec![(12.0, 18.5), (-3.0, -1.0)], positions);
+    let object = object_group.objects().nth(1).unwrap();
+    assert_matches!(object.shape(), Some(&Shape::Polygon(ref polygon)) if polygon.points().nth(1) == Some(&Point {x: 4, y: 4}));
+}
+
+#[test]
+fn after_reading_manual_draw_order_expect_index() {
+    let map = Map::from_str(r#"<map><objectgroup draworder="manual"/></map>"#).unwrap();
+    assert_eq!(DrawOrder::Index, map.object_groups().next().unwrap().draw_order());
+}
+
+#[test]
+fn after_reading_object_groups_expect_objects_in_their_draw_order() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup draworder="index">
+            <object id="3"/>
+            <object id="1"/>
+            <object id="2"/>
+        </objectgroup>
+        <objectgroup draworder="topdown">
+            <object id="3"/>
+            <object id="1"/>
+            <object id="2"/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let mut object_groups = map.object_groups();
+    let index: Vec<_> = object_groups.next().unwrap().objects_in_draw_order().iter().map(|object| object.id()).collect();
+    assert_eq!(vec![1, 2, 3], index);
+    let topdown: Vec<_> = object_groups.next().unwrap().objects_in_draw_order().iter().map(|object| object.id()).collect();
+    assert_eq!(vec![3, 1, 2], topdown);
+}
+
+#[test]
+fn when_extracting_object_groups_expect_only_map_object_groups() {
+    let xml = r#"<map width="2" height="2">
+        <tileset firstgid="1" name="ground">
+            <tile id="0">
+                <objectgroup name="tile collision">
+                    <object/>
+                </objectgroup>
+            </tile>
+        </tileset>
+        <layer name="ground">
+            <data encoding="csv">1,1,1,1</data>
+        </layer>
+        <objectgroup name="walls">
+            <object id="1"/>
+            <object id="2"/>
+        </objectgroup>
+        <group name="upper">
+            <objectgroup name="triggers">
+                <object id="3"/>
+            </objectgroup>
+        </group>
+    </map>"#;
+    let object_groups = object_groups_from_reader(xml.as_bytes()).unwrap();
+    assert_eq!(2, object_groups.len());
+    assert_eq!("walls", object_groups[0].name());
+    assert_eq!(2, object_groups[0].objects().count());
+    assert_eq!("triggers", object_groups[1].name());
+    assert_eq!(3, object_groups[1].objects().next().unwrap().id());
+
+    let result = object_groups_from_reader("<tileset/>".as_bytes());
+    assert_matches!(result, Err(Error::BadXml(ref name)) if name == "tileset");
+}
+
+#[test]
+fn after_reading_object_groups_from_bom_or_utf16_xml_expect_object_groups() {
+    let xml = "\u{feff}<map><objectgroup name=\"walls\"/></map>";
+    let object_groups = object_groups_from_reader(xml.as_bytes()).unwrap();
+    assert_eq!("walls", object_groups[0].name());
+
+    let xml = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><map><objectgroup name=\"walls\"/></map>";
+    let bytes: Vec<u8> = xml.encode_utf16().flat_map(|unit| vec![unit as u8, (unit >> 8) as u8]).collect();
+    let object_groups = object_groups_from_reader(&bytes[..]).unwrap();
+    assert_eq!("walls", object_groups[0].name());
+}
+
 #[test]
 fn after_reading_xml_with_objects_expect_object_groups_to_be_iterable_over_objects() {
     let map = get_map_with_objects();
@@ -285,7 +1525,7 @@ fn after_reading_xml_with_objects_expect_object_groups_to_be_iterable_over_objec
     let object = objects.next().unwrap();
     assert!(object.is_visible());
     assert_eq!(None, object.gid());
-    assert_eq!(0, object.properties().count());
+    assert_eq!(0, object.properties().len());
     assert_eq!(None, object.shape());
 
     let object = objects.next().unwrap();
@@ -301,7 +1541,7 @@ fn after_reading_xml_with_objects_expect_object_groups_to_be_iterable_over_objec
     assert_eq!(Some(123), object.gid());
 
     let object = objects.next().unwrap();
-    assert_eq!(1, object.properties().count());
+    assert_eq!(1, object.properties().len());
 
     let object = objects.next().unwrap();
     assert_matches!(object.shape(), Some(&Shape::Ellipse));
@@ -320,12 +1560,98 @@ fn after_reading_xml_with_objects_expect_object_groups_to_be_iterable_over_objec
     assert_matches!(object.shape(), Some(&Shape::Polyline(..)));
 }
 
+#[test]
+fn after_reading_object_with_type_or_class_expect_class() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object type="legacy"/>
+            <object class="modern"/>
+            <object class="modern" type="legacy"/>
+            <object type="legacy" class="modern"/>
+            <object/>
+        </objectgroup>
+    </map>"#).unwrap();
+    let classes: Vec<_> = map.object_groups().next().unwrap().objects().map(Object::class).collect();
+    assert_eq!(vec![Some("legacy"), Some("modern"), Some("modern"), Some("modern"), None], classes);
+}
+
+#[test]
+fn expect_property_accessors_to_borrow_stored_strings() {
+    let property = Property::new("name", "value", PropertyType::String);
+    assert_eq!(property.name().as_ptr(), property.name().as_ptr());
+    assert_eq!(property.value().as_ptr(), property.value().as_ptr());
+}
+
+#[test]
+fn after_reading_xml_with_object_properties_expect_typed_access() {
+    let map = Map::from_str(r#"<map>
+        <objectgroup>
+            <object>
+                <properties>
+                    <property name="hp" value="42" type="int"/>
+                    <property name="speed" value="1.5" type="float"/>
+                    <property name="solid" value="true" type="bool"/>
+                </properties>
+            </object>
+        </objectgroup>
+    </map>"#).unwrap();
+    let object = map.object_groups().next().unwrap().objects().next().unwrap();
+    assert_eq!(3, object.properties().len());
+
+    let hp = object.property("hp").unwrap();
+    assert_eq!(PropertyType::Int, hp.property_type());
+    assert_eq!(Some(42), hp.as_int());
+    assert_eq!(Some(1.5), object.property("speed").and_then(Property::as_float));
+    assert_eq!(Some(true), object.property("solid").and_then(Property::as_bool));
+    assert_eq!(None, object.property("speed").and_then(Property::as_int));
+    assert!(object.property("missing").is_none());
+}
+
+#[test]
+fn after_reading_text_object_expect_multiline_content_to_be_preserved() {
+    let map = Map::from_str("<map>
+        <objectgroup>
+            <object>
+                <text fontfamily=\"serif\" pixelsize=\"12\" wrap=\"1\" halign=\"center\">First line
+Second line<![CDATA[ <third> ]]>end</text>
+            </object>
+        </objectgroup>
+    </map>").unwrap();
+    let object = map.object_groups().next().unwrap().objects().next().unwrap();
+    match object.shape() {
+        Some(&Shape::Text(ref text)) => {
+            assert_eq!("First line\nSecond line <third> end", text.content());
+            assert_eq!("serif", text.font_family());
+            assert_eq!(12, text.pixel_size());
+            assert!(text.wrap());
+            assert_eq!(HAlign::Center, text.halign());
+            assert_eq!(VAlign::Top, text.valign());
+        }
+        other => panic!("unexpected shape: {:?}", other),
+    }
+}
+
 #[test]
 fn after_reading_valid_xml_expect_tileset_to_have_first_gid() {
     let tileset = get_simple_valid_tileset();
     assert_eq!(1, tileset.first_gid());
 }
 
+#[test]
+fn after_reading_external_tileset_expect_versions() {
+    let tileset = Tileset::from_str(r#"<tileset version="1.8" tiledversion="1.8.2" name="external" tilewidth="8" tileheight="8"/>"#).unwrap();
+    assert_eq!("1.8", tileset.version());
+    assert_eq!("1.8.2", tileset.tiled_version());
+    assert_eq!("", get_simple_valid_tileset().version());
+}
+
+#[test]
+fn after_reading_tileset_with_class_expect_class() {
+    let tileset = Tileset::from_str(r#"<tileset name="ground" class="terrain" tilewidth="8" tileheight="8"/>"#).unwrap();
+    assert_eq!(Some("terrain"), tileset.class());
+    assert_eq!(None, get_simple_valid_tileset().class());
+}
+
 #[test]
 fn after_reading_valid_xml_expect_tileset_to_have_name() {
     let tileset = get_simple_valid_tileset();
@@ -362,10 +1688,21 @@ fn after_reading_valid_xml_expect_tileset_to_have_tile_count() {
     assert_eq!(100, tileset.tile_count());
 }
 
+#[test]
+fn after_reading_tileset_reference_expect_attribute_order_not_to_matter() {
+    let source_first = Map::from_str(r#"<map><tileset source="some_file.tsx" firstgid="5"/></map>"#).unwrap();
+    let gid_first = Map::from_str(r#"<map><tileset firstgid="5" source="some_file.tsx"/></map>"#).unwrap();
+    for map in &[source_first, gid_first] {
+        let tileset = map.tilesets().next().unwrap();
+        assert_eq!(5, tileset.first_gid());
+        assert_eq!("some_file.tsx", tileset.source());
+    }
+}
+
 #[test]
 fn after_reading_valid_xml_expect_tileset_to_have_source() {
-    let tileset = get_simple_valid_tileset();
-    assert_eq!("some_file.tsx", tileset.source());
+    let map = Map::from_str(r#"<map><tileset firstgid="1" source="some_file.tsx"/></map>"#).unwrap();
+    assert_eq!("some_file.tsx", map.tilesets().next().unwrap().source());
 }
 
 #[test]
@@ -377,7 +1714,7 @@ fn after_reading_valid_xml_expect_tileset_to_have_columns() {
 #[test]
 fn after_reading_valid_xml_with_image_element_expect_tileset_to_have_image() {
     let tileset = Tileset::from_str(
-        r#"<tileset>
+        r#"<tileset tilewidth="8" tileheight="8">
         <image format="png"
                source="some_file.png"
                trans="FF00FF"
@@ -395,10 +1732,67 @@ fn after_reading_valid_xml_with_image_element_expect_tileset_to_have_image() {
     assert!(image.data().is_some());
 }
 
+#[test]
+fn after_reading_embedded_gif_image_expect_format_and_embedded_data() {
+    let tileset = Tileset::from_str(r#"<tileset tilewidth="8" tileheight="8">
+        <image format="gif" width="1" height="1">
+            <data encoding="base64">R0lGODlhAQABAAAAACw=</data>
+        </image>
+    </tileset>"#).unwrap();
+    let image = tileset.image().unwrap();
+    assert_eq!("gif", image.format());
+    assert!(image.has_embedded_data());
+    assert_eq!(Some("R0lGODlhAQABAAAAACw="), image.data().unwrap().raw_content());
+
+    let tileset = Tileset::from_str(r#"<tileset tilewidth="8" tileheight="8"><image source="sheet.png"/></tileset>"#).unwrap();
+    assert!(!tileset.image().unwrap().has_embedded_data());
+}
+
+#[test]
+fn when_reading_embedded_image_with_missing_or_unknown_format_expect_format_error() {
+    let result = Tileset::from_str(r#"<tileset tilewidth="8" tileheight="8">
+        <image><data encoding="base64">R0lGODlh</data></image>
+    </tileset>"#);
+    assert_matches!(result, Err(Error::BadImageFormat(ref format)) if format.is_empty());
+
+    let xml = r#"<map width="1" height="1" tilewidth="8" tileheight="8">
+        <tileset firstgid="1"><image format="tiff" source="sheet.tiff"/></tileset>
+    </map>"#;
+    let result = Map::from_str_with_mode(xml, ReadMode::Strict);
+    assert_matches!(result, Err(Error::BadImageFormat(ref format)) if format == "tiff");
+}
+
+#[test]
+fn after_reading_image_with_unlisted_format_expect_it_to_be_kept() {
+    for format in &["jpeg", "webp", "tga"] {
+        let xml = format!(r#"<tileset tilewidth="8" tileheight="8"><image format="{}" source="sheet"/></tileset>"#, format);
+        let tileset = Tileset::from_str(&xml).unwrap();
+        assert_eq!(*format, tileset.image().unwrap().format());
+    }
+}
+
+#[test]
+fn after_reading_tileset_image_without_dimensions_expect_no_tile_rect() {
+    let tileset = Tileset::from_str(r#"<tileset tilewidth="16" tileheight="16" tilecount="4" columns="2">
+        <image source="sheet.png"/>
+    </tileset>"#).unwrap();
+    assert!(!tileset.image().unwrap().has_dimensions());
+    assert_eq!(None, tileset.tile_rect(0));
+
+    let tileset = Tileset::from_str(r#"<tileset tilewidth="16" tileheight="16" tilecount="4"
+            spacing="2" margin="1">
+        <image source="sheet.png" width="36" height="36"/>
+    </tileset>"#).unwrap();
+    assert!(tileset.image().unwrap().has_dimensions());
+    assert_eq!(Some((1, 1, 16, 16)), tileset.tile_rect(0));
+    assert_eq!(Some((19, 19, 16, 16)), tileset.tile_rect(3));
+    assert_eq!(None, tileset.tile_rect(4));
+}
+
 #[test]
 fn after_reading_valid_xml_with_properties_expect_tileset_to_have_properties() {
     let tileset = Tileset::from_str(
-        r#"<tileset>
+        r#"<tileset tilewidth="8" tileheight="8">
         <properties>
             <property name="prop1_name" value="prop1_value"/>
             <property name="prop2_name" value="0" type="int"/>
@@ -406,8 +1800,8 @@ fn after_reading_valid_xml_with_properties_expect_tileset_to_have_properties() {
             <property name="prop4_name" value="true" type="bool"/>
         </properties>
     <tileset>"#).unwrap();
-    assert_eq!(4, tileset.properties().count());
-    let mut props = tileset.properties();
+    assert_eq!(4, tileset.properties().len());
+    let mut props = tileset.properties().iter();
 
     let prop1 = props.next().unwrap();
     assert_eq!("prop1_name", prop1.name());
@@ -427,7 +1821,7 @@ fn after_reading_valid_xml_with_properties_expect_tileset_to_have_properties() {
 #[test]
 fn after_reading_valid_xml_with_tile_offset_expect_tileset_to_have_tile_offset() {
     let tileset = Tileset::from_str(
-        r#"<tileset>
+        r#"<tileset tilewidth="8" tileheight="8">
         <tileoffset x="0" y="1"/>
     <tileset>"#).unwrap();
     let offset = tileset.tile_offset().unwrap();
@@ -438,31 +1832,46 @@ fn after_reading_valid_xml_with_tile_offset_expect_tileset_to_have_tile_offset()
 #[test]
 fn after_reading_valid_xml_with_terrains_expect_tileset_to_have_terrains() {
     let tileset = Tileset::from_str(r#"
-    <tileset>
+    <tileset tilewidth="8" tileheight="8">
         <terraintypes>
             <terrain name="terrain1"/>
-            <terrain tile="tile-id">
+            <terrain tile="5">
                 <properties>
                     <property/>
                 </properties>
             </terrain>
+            <terrain name="terrain3" tile="-1"/>
         </terraintypes>
     <tileset>"#).unwrap();
-    assert_eq!(2, tileset.terrain_types().count());
+    assert_eq!(3, tileset.terrain_types().count());
     let mut terrain_types = tileset.terrain_types();
 
     let terrain1 = terrain_types.next().unwrap();
     assert_eq!("terrain1", terrain1.name());
+    assert_eq!(None, terrain1.tile());
 
     let terrain2 = terrain_types.next().unwrap();
-    assert_eq!("tile-id", terrain2.tile());
-    assert_eq!(1, terrain2.properties().count());
+    assert_eq!(Some(5), terrain2.tile());
+    assert_eq!(1, terrain2.properties().len());
+
+    let terrain3 = terrain_types.next().unwrap();
+    assert_eq!(None, terrain3.tile());
+}
+
+#[test]
+fn when_reading_terrain_with_non_numeric_tile_expect_terrain_error() {
+    let result = Tileset::from_str(r#"<tileset tilewidth="8" tileheight="8">
+        <terraintypes>
+            <terrain tile="tile-id"/>
+        </terraintypes>
+    </tileset>"#);
+    assert_matches!(result, Err(Error::InvalidTerrain(ref tile)) if tile == "tile-id");
 }
 
 #[test]
 fn after_reading_valid_xml_with_tiles_expect_tileset_to_be_iterable_over_tiles() {
     let tileset = Tileset::from_str(r#"
-    <tileset>
+    <tileset tilewidth="8" tileheight="8">
         <tile id="123">
             <properties>
                 <property name="some_name" value="some_value"/>
@@ -486,7 +1895,7 @@ fn after_reading_valid_xml_with_tiles_expect_tileset_to_be_iterable_over_tiles()
 
     let tile1 = tileset.tiles().next().unwrap();
     assert_eq!(123, tile1.id());
-    assert_eq!(1, tile1.properties().count());
+    assert_eq!(1, tile1.properties().len());
 
     let tile2 = tileset.tiles().nth(1).unwrap();
     assert!(tile2.image().is_some());
@@ -509,6 +1918,238 @@ fn after_reading_valid_xml_with_tiles_expect_tileset_to_be_iterable_over_tiles()
     assert_eq!(Some(&Corners(0, 1, 2, 3)), tile6.terrain());
 }
 
+#[test]
+fn after_reading_tileset_expect_tiles_to_be_found_by_id() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilecount="8" tilewidth="8" tileheight="8">
+        <tile id="2" probability="0.5"/>
+        <tile id="5">
+            <animation>
+                <frame tileid="5" duration="100"/>
+                <frame tileid="6" duration="100"/>
+            </animation>
+        </tile>
+    </tileset>"#).unwrap();
+    let tile = tileset.tile(5).unwrap();
+    assert_eq!(5, tile.id());
+    assert_eq!(2, tile.animation().unwrap().frames().len());
+    assert_eq!(Some(0.5), tileset.tile(2).unwrap().probability());
+    assert!(tileset.tile(3).is_none());
+}
+
+#[test]
+fn when_reading_standalone_tileset_with_source_expect_unexpected_attribute_error() {
+    let result = Tileset::from_str(r#"<tileset source="other.tsx" tilewidth="8" tileheight="8"/>"#);
+    assert_matches!(result, Err(Error::UnexpectedAttribute(ref attr)) if attr == "source");
+}
+
+#[test]
+fn after_reading_tileset_without_tile_count_expect_it_to_be_computed() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilewidth="16" tileheight="16" spacing="2" margin="1" columns="4">
+        <image source="sheet.png" width="72" height="54"/>
+    </tileset>"#).unwrap();
+    assert_eq!(12, tileset.tile_count());
+    let collection = Tileset::from_str(r#"<tileset tilewidth="16" tileheight="16"/>"#).unwrap();
+    assert_eq!(0, collection.tile_count());
+}
+
+#[test]
+fn after_reading_sheet_without_tile_count_expect_tile_rects_within_the_image() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilewidth="16" tileheight="16">
+        <image source="sheet.png" width="64" height="32"/>
+    </tileset>"#).unwrap();
+    assert_eq!(Some((48, 16, 16, 16)), tileset.tile_rect(7));
+    assert_eq!(None, tileset.tile_rect(8));
+    assert_eq!(None, tileset.tile_rect(100));
+}
+
+#[test]
+fn after_reading_sheet_tileset_expect_normalized_uv_rects() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilewidth="16" tileheight="16" tilecount="6" columns="3">
+        <image source="sheet.png" width="64" height="32"/>
+    </tileset>"#).unwrap();
+    let uvs = tileset.uv_rects();
+    assert_eq!(6, uvs.len());
+    assert_eq!([0.0, 0.0, 0.25, 0.5], uvs[0]);
+    assert_eq!([0.5, 0.5, 0.75, 1.0], uvs[5]);
+    let collection = Tileset::from_str(r#"
+    <tileset tilewidth="16" tileheight="16" tilecount="1">
+        <tile id="0"><image source="a.png" width="16" height="16"/></tile>
+    </tileset>"#).unwrap();
+    assert!(collection.uv_rects().is_empty());
+}
+
+#[test]
+fn after_reading_animated_tile_expect_total_duration() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilecount="4" tilewidth="8" tileheight="8">
+        <tile id="0">
+            <animation>
+                <frame tileid="0" duration="100"/>
+                <frame tileid="1" duration="200"/>
+                <frame tileid="2" duration="100"/>
+            </animation>
+        </tile>
+        <tile id="1" probability="0.5"/>
+    </tileset>"#).unwrap();
+    assert_eq!(Some(400), tileset.tile(0).unwrap().animation_duration());
+    assert_eq!(None, tileset.tile(1).unwrap().animation_duration());
+}
+
+#[test]
+fn after_reading_collection_tileset_expect_tile_sizes_from_their_images() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilecount="2" tilewidth="8" tileheight="8">
+        <tile id="0">
+            <image source="tree.png" width="24" height="24"/>
+        </tile>
+        <tile id="1">
+            <image source="rock.png"/>
+        </tile>
+    </tileset>"#).unwrap();
+    assert_eq!(Some((24, 24)), tileset.tile(0).unwrap().size());
+    assert_eq!(None, tileset.tile(1).unwrap().size());
+}
+
+#[test]
+fn after_reading_tiles_with_terrain_expect_corner_terrain_indices() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilewidth="8" tileheight="8">
+        <tile id="0" terrain="0,1,2,3"/>
+        <tile id="4" terrain=",0,,1"/>
+        <tile id="5"/>
+    </tileset>"#).unwrap();
+    assert_eq!(Some([Some(0), Some(1), Some(2), Some(3)]), tileset.terrain_corners(0));
+    assert_eq!(Some([None, Some(0), None, Some(1)]), tileset.terrain_corners(4));
+    assert!(tileset.tiles().nth(1).unwrap().terrain().is_none());
+    assert_eq!(None, tileset.terrain_corners(5));
+    assert_eq!(None, tileset.terrain_corners(1));
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn after_reading_zstd_compressed_layer_expect_decoded_tiles() {
+    let map = Map::from_str(r#"<map>
+        <layer width="2" height="2">
+            <data encoding="base64" compression="zstd">KLUv/QBYgQAAAQAAAAIAAAADAAAABAAAgA==</data>
+        </layer>
+    </map>"#).unwrap();
+    let gids: Vec<_> = map.layers().next().unwrap().data().unwrap().tiles().map(|tile| tile.gid).collect();
+    assert_eq!(vec![1, 2, 3, 2147483652], gids);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn expect_weighted_tile_pick_to_follow_probabilities() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let tileset = Tileset::from_str(r#"
+    <tileset tilecount="4" tilewidth="8" tileheight="8">
+        <tile id="0" probability="0"/>
+        <tile id="1" probability="0.5"/>
+        <tile id="3" probability="0"/>
+    </tileset>"#).unwrap();
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut counts = [0; 4];
+    for _ in 0..3000 {
+        let id = tileset.pick_weighted(&mut rng).unwrap();
+        counts[id as usize] += 1;
+    }
+    assert_eq!(0, counts[0]);
+    assert_eq!(0, counts[3]);
+    // Tile 2 has the default weight of 1.0, twice the weight of tile 1.
+    assert!(counts[1] > 800 && counts[1] < 1200);
+    assert!(counts[2] > 1800 && counts[2] < 2200);
+
+    let empty = Tileset::from_str(r#"<tileset tilewidth="8" tileheight="8"><tile probability="0"/></tileset>"#).unwrap();
+    assert_eq!(None, empty.pick_weighted(&mut rng));
+
+    let sheet = Tileset::from_str(r#"
+    <tileset tilewidth="16" tileheight="16">
+        <image source="sheet.png" width="32" height="16"/>
+        <tile id="0" probability="0"/>
+    </tileset>"#).unwrap();
+    assert_eq!(Some(1), sheet.pick_weighted(&mut rng));
+}
+
+#[test]
+fn after_reading_tileset_with_animations_expect_animated_tiles() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilewidth="8" tileheight="8">
+        <tile id="1"/>
+        <tile id="2">
+            <animation>
+                <frame tileid="2" duration="100"/>
+                <frame tileid="3" duration="200"/>
+            </animation>
+        </tile>
+        <tile id="4">
+            <properties>
+                <property name="static" value="true"/>
+            </properties>
+        </tile>
+    </tileset>"#).unwrap();
+    let animated: Vec<_> = tileset.animated_tiles().collect();
+    assert_eq!(1, animated.len());
+    let (id, frames) = animated[0];
+    assert_eq!(2, id);
+    assert_eq!(2, frames.len());
+    assert_eq!(3, frames[1].tile_id());
+    assert_eq!(200, frames[1].duration());
+}
+
+#[test]
+fn after_reading_tile_collision_group_expect_draw_order() {
+    let tileset = Tileset::from_str(r#"
+    <tileset tilewidth="8" tileheight="8">
+        <tile id="0">
+            <objectgroup draworder="index">
+                <object id="1" x="0" y="0" width="8" height="8"/>
+            </objectgroup>
+        </tile>
+    </tileset>"#).unwrap();
+    let group = tileset.tiles().next().unwrap().object_group().unwrap();
+    assert_eq!(DrawOrder::Index, group.draw_order());
+    assert_eq!(1, group.objects().count());
+}
+
+#[test]
+fn when_reading_frame_without_duration_or_tile_id_expect_missing_attribute_error() {
+    let result = Tileset::from_str(r#"
+    <tileset tilewidth="8" tileheight="8">
+        <tile id="1">
+            <animation>
+                <frame tileid="2"/>
+            </animation>
+        </tile>
+    </tileset>"#);
+    assert_matches!(result, Err(Error::MissingAttribute(ref name)) if name == "duration");
+
+    let result = Tileset::from_str(r#"
+    <tileset tilewidth="8" tileheight="8">
+        <tile id="1">
+            <animation>
+                <frame duration="100"/>
+            </animation>
+        </tile>
+    </tileset>"#);
+    assert_matches!(result, Err(Error::MissingAttribute(ref name)) if name == "tileid");
+}
+
+#[test]
+fn when_reading_polygon_with_incomplete_last_point_expect_invalid_point_error() {
+    let result = Map::from_str(r#"<map>
+        <objectgroup>
+            <object id="1"><polygon points="0,0 32"/></object>
+        </objectgroup>
+    </map>"#);
+    assert_matches!(result, Err(Error::InvalidPoint(ref point)) if point == "32");
+}
+
 #[test]
 fn expect_point_to_be_constructible_from_comma_separated_pair_of_ints() {
     assert_matches!(Point::from_str(""), Err(Error::InvalidPoint(..)));
@@ -537,7 +2178,8 @@ fn get_map_with_tilesets() -> Map {
 }
 
 fn get_map_with_layers() -> Map {
-    Map::from_str(r#"<map>
+    // The encoded layer is a placeholder, so its decoding is left to the caller
+    let xml = r#"<map>
         <layer name="layer1_name"/>
         <layer name="layer2_name" opacity="0"/>
         <layer name="layer3_name" visible="0"/>
@@ -557,7 +2199,10 @@ fn get_map_with_layers() -> Map {
         <layer>
             <data encoding="base64" compression="gzip">SOME_ENCODED_AND_COMPRESSED_DATA</data>
         </layer>
-    </map>"#).unwrap()
+    </map>"#;
+    let mut reader = TmxReader::new(xml.as_bytes());
+    reader.set_decode_tile_data(false);
+    reader.read_map().unwrap()
 }
 
 fn get_map_with_image_layers() -> Map {
@@ -584,7 +2229,6 @@ fn get_map_with_image_layers() -> Map {
 fn get_simple_valid_tileset() -> Tileset {
     Tileset::from_str(r#"<tileset firstgid="1"
                 name="simple"
-                source="some_file.tsx"
                 tilewidth="32"
                 tileheight="16"
                 spacing="4"
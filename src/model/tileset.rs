@@ -13,17 +13,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::str::FromStr;
 use std::path::Path;
 use std::fs::File;
 
 use xml::attribute::OwnedAttribute;
 
+#[cfg(feature = "rand")]
+use rand::Rng;
+
 use error::Error;
 use model::image::Image;
 use model::map::ObjectGroup;
-use model::property::{Properties, PropertyCollection};
+use model::property::Properties;
 use model::reader::{self, TmxReader, ElementReader};
 
 define_iterator_wrapper!(Tiles, Tile);
@@ -33,7 +36,10 @@ define_iterator_wrapper!(TerrainTypes, Terrain);
 pub struct Tileset {
     first_gid: u32,
     source: String,
+    version: String,
+    tiled_version: String,
     name: String,
+    class: Option<String>,
     tile_width: u32,
     tile_height: u32,
     spacing: u32,
@@ -42,15 +48,22 @@ pub struct Tileset {
     columns: u32,
     image: Option<Image>,
     tile_offset: Option<TileOffset>,
-    properties: PropertyCollection,
+    properties: Properties,
     terrain_types: TerrainCollection,
     tiles: Vec<Tile>,
 }
 
 impl Tileset {
+    /// Reads an external `.tsx` tileset, which must give its tile size as
+    /// there is no map to take it from.
     pub fn open<P: AsRef<Path>>(path: P) -> ::Result<Tileset> {
         let file = try!(File::open(path));
-        let mut reader = TmxReader::new(file);
+        Tileset::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader<R: BufRead>(source: R) -> ::Result<Tileset> {
+        let source = try!(reader::utf8_source(source));
+        let mut reader = TmxReader::new(source);
         reader.read_tileset()
     }
 
@@ -70,6 +83,24 @@ impl Tileset {
         self.source = source.into();
     }
 
+    /// Returns the format version of an external tileset file.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn set_version<S: Into<String>>(&mut self, version: S) {
+        self.version = version.into();
+    }
+
+    /// Returns the version of Tiled that saved an external tileset file.
+    pub fn tiled_version(&self) -> &str {
+        &self.tiled_version
+    }
+
+    fn set_tiled_version<S: Into<String>>(&mut self, tiled_version: S) {
+        self.tiled_version = tiled_version.into();
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -78,6 +109,14 @@ impl Tileset {
         self.name = name.into();
     }
 
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_ref().map(String::as_str)
+    }
+
+    fn set_class<S: Into<String>>(&mut self, class: S) {
+        self.class = Some(class.into());
+    }
+
     pub fn tile_width(&self) -> u32 {
         self.tile_width
     }
@@ -94,6 +133,20 @@ impl Tileset {
         self.tile_height = tile_height;
     }
 
+    /// Gives the tileset the tile size of its map where it has none. References
+    /// to external tilesets are left alone, as their size is in another file.
+    pub(crate) fn inherit_tile_size(&mut self, tile_width: u32, tile_height: u32) {
+        if !self.source.is_empty() {
+            return;
+        }
+        if self.tile_width == 0 {
+            self.tile_width = tile_width;
+        }
+        if self.tile_height == 0 {
+            self.tile_height = tile_height;
+        }
+    }
+
     pub fn spacing(&self) -> u32 {
         self.spacing
     }
@@ -110,8 +163,16 @@ impl Tileset {
         self.margin = margin;
     }
 
+    /// Returns the number of tiles. When the file does not give it, it is
+    /// computed from the image size, or 0 if that is not known either.
     pub fn tile_count(&self) -> u32 {
-        self.tile_count
+        if self.tile_count > 0 {
+            return self.tile_count;
+        }
+        match self.grid_size() {
+            Some((columns, rows)) => columns * rows,
+            None => 0,
+        }
     }
 
     fn set_tile_count(&mut self, tile_count: u32) {
@@ -134,11 +195,11 @@ impl Tileset {
         self.tile_offset = Some(tile_offset);
     }
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+    pub fn properties(&self) -> &Properties {
+        &self.properties
     }
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
+    fn set_properties(&mut self, properties: Properties) {
         self.properties = properties;
     }
 
@@ -165,14 +226,141 @@ impl Tileset {
     fn add_tile(&mut self, tile: Tile) {
         self.tiles.push(tile);
     }
+
+    /// Returns the `<tile>` entry of the tile `local_id`, or `None` if the
+    /// tile has no explicit definition.
+    pub fn tile(&self, local_id: u32) -> Option<&Tile> {
+        self.tiles.iter().find(|tile| tile.id == local_id)
+    }
+
+    /// Returns the source rectangle of a tile within the tileset image, as
+    /// `(x, y, width, height)` in pixels.
+    ///
+    /// Returns `None` for collection tilesets, for ids beyond the tile count,
+    /// and when the image dimensions are not known.
+    pub fn tile_rect(&self, local_id: u32) -> Option<(u32, u32, u32, u32)> {
+        let (columns, _) = self.grid_size()?;
+        if local_id >= self.tile_count() {
+            return None;
+        }
+        if columns == 0 {
+            return None;
+        }
+        let x = self.margin + (local_id % columns) * (self.tile_width + self.spacing);
+        let y = self.margin + (local_id / columns) * (self.tile_height + self.spacing);
+        Some((x, y, self.tile_width, self.tile_height))
+    }
+
+    /// Returns the texture coordinates `[u0, v0, u1, v1]` of every tile
+    /// within the tileset image, normalized to 0..1, in local id order.
+    ///
+    /// Returns an empty vector for collection tilesets and when the image
+    /// dimensions are not known.
+    pub fn uv_rects(&self) -> Vec<[f32; 4]> {
+        let (width, height) = match self.image {
+            Some(ref image) if image.has_dimensions() => (image.width() as f32, image.height() as f32),
+            _ => return Vec::new(),
+        };
+        (0..self.tile_count())
+            .filter_map(|id| self.tile_rect(id))
+            .map(|(x, y, w, h)| [x as f32 / width, y as f32 / height, (x + w) as f32 / width, (y + h) as f32 / height])
+            .collect()
+    }
+
+    /// Returns the number of tile columns and rows fitting in the image,
+    /// preferring the `columns` attribute when given.
+    fn grid_size(&self) -> Option<(u32, u32)> {
+        let image = match self.image {
+            Some(ref image) if image.has_dimensions() => image,
+            _ => return None,
+        };
+        if self.tile_width == 0 || self.tile_height == 0 {
+            return None;
+        }
+        let columns = if self.columns > 0 {
+            self.columns
+        } else {
+            (image.width() + self.spacing).saturating_sub(2 * self.margin) / (self.tile_width + self.spacing)
+        };
+        let rows = (image.height() + self.spacing).saturating_sub(2 * self.margin) / (self.tile_height + self.spacing);
+        Some((columns, rows))
+    }
+
+    /// Returns the terrain indices of the top-left, top-right, bottom-left
+    /// and bottom-right corners of a tile, as listed in `terrain_types()`.
+    ///
+    /// Returns `None` if the tile has no terrain information.
+    pub fn terrain_corners(&self, local_id: u32) -> Option<[Option<u32>; 4]> {
+        self.tile(local_id).and_then(Tile::terrain_corners)
+    }
+
+    /// Returns an iterator over the tiles having a non-empty animation,
+    /// yielding their local id along with their frames.
+    pub fn animated_tiles(&self) -> AnimatedTiles {
+        AnimatedTiles(self.tiles.iter())
+    }
+
+    /// Picks a local tile id at random, weighted by the tile probabilities.
+    ///
+    /// Tiles without an explicit probability are given a weight of 1.0.
+    /// Returns `None` if no tile has a positive weight.
+    #[cfg(feature = "rand")]
+    pub fn pick_weighted<R: Rng>(&self, rng: &mut R) -> Option<u32> {
+        let weight = |id: u32| {
+            self.tiles.iter()
+                .find(|tile| tile.id() == id)
+                .and_then(Tile::probability)
+                .unwrap_or(1.0)
+        };
+        let tile_count = self.tile_count();
+        let candidates: Vec<(u32, f32)> = if tile_count > 0 {
+            (0..tile_count).map(|id| (id, weight(id))).collect()
+        } else {
+            self.tiles.iter().map(|tile| (tile.id(), tile.probability().unwrap_or(1.0))).collect()
+        };
+
+        let total: f32 = candidates.iter().map(|&(_, w)| w).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut remaining = rng.gen::<f32>() * total;
+        let mut picked = None;
+        for &(id, w) in &candidates {
+            if w <= 0.0 {
+                continue;
+            }
+            picked = Some(id);
+            if remaining < w {
+                break;
+            }
+            remaining -= w;
+        }
+        picked
+    }
+}
+
+pub struct AnimatedTiles<'a>(::std::slice::Iter<'a, Tile>);
+
+impl<'a> Iterator for AnimatedTiles<'a> {
+    type Item = (u32, &'a [Frame]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for tile in &mut self.0 {
+            if let Some(animation) = tile.animation() {
+                if !animation.frames().is_empty() {
+                    return Some((tile.id(), animation.frames()));
+                }
+            }
+        }
+        None
+    }
 }
 
 impl FromStr for Tileset {
     type Err = Error;
 
     fn from_str(s: &str) -> ::Result<Tileset> {
-        let mut tsx = TmxReader::new(s.as_bytes());
-        tsx.read_tileset()
+        Tileset::from_reader(s.as_bytes())
     }
 }
 
@@ -224,11 +412,12 @@ impl TerrainCollection {
 pub struct Tile {
     id: u32,
     corners: Option<Corners>,
+    terrain_corners: Option<[Option<u32>; 4]>,
     probability: Option<f32>,
     animation: Option<Animation>,
     image: Option<Image>,
     object_group: Option<ObjectGroup>,
-    properties: PropertyCollection,
+    properties: Properties,
 }
 
 impl Tile {
@@ -244,8 +433,17 @@ impl Tile {
         self.corners.as_ref()
     }
 
-    fn set_corners(&mut self, corners: Corners) {
-        self.corners = Some(corners);
+    /// Returns the terrain of each corner, where corners without a terrain
+    /// are `None`. Unlike `terrain()`, this is available for partial terrains.
+    pub fn terrain_corners(&self) -> Option<[Option<u32>; 4]> {
+        self.terrain_corners
+    }
+
+    fn set_terrain_corners(&mut self, corners: [Option<u32>; 4]) {
+        if let [Some(top_left), Some(top_right), Some(bottom_left), Some(bottom_right)] = corners {
+            self.corners = Some(Corners(top_left, top_right, bottom_left, bottom_right));
+        }
+        self.terrain_corners = Some(corners);
     }
 
     pub fn probability(&self) -> Option<f32> {
@@ -256,11 +454,11 @@ impl Tile {
         self.probability = Some(probability);
     }
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+    pub fn properties(&self) -> &Properties {
+        &self.properties
     }
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
+    fn set_properties(&mut self, properties: Properties) {
         self.properties = properties;
     }
 
@@ -272,6 +470,15 @@ impl Tile {
         self.image = Some(image);
     }
 
+    /// Returns the size of the own image of the tile, as found in collection
+    /// tilesets, when the file gives it.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        match self.image {
+            Some(ref image) if image.has_dimensions() => Some((image.width(), image.height())),
+            _ => None,
+        }
+    }
+
     pub fn object_group(&self) -> Option<&ObjectGroup> {
         self.object_group.as_ref()
     }
@@ -287,6 +494,14 @@ impl Tile {
     fn set_animation(&mut self, animation: Animation) {
         self.animation = Some(animation);
     }
+
+    /// Returns the time in milliseconds for one loop of the animation, or
+    /// `None` if the tile has no frames.
+    pub fn animation_duration(&self) -> Option<u32> {
+        self.animation()
+            .filter(|animation| !animation.frames().is_empty())
+            .map(|animation| animation.frames().iter().map(Frame::duration).sum())
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -305,18 +520,42 @@ impl FromStr for Corners {
     }
 }
 
+fn read_terrain_corners(value: &str) -> ::Result<[Option<u32>; 4]> {
+    let mut corners = [None; 4];
+    let mut count = 0;
+    for id in value.split(',') {
+        if count == 4 {
+            return Err(Error::InvalidTerrain(value.to_string()));
+        }
+        if !id.is_empty() {
+            corners[count] = Some(try!(reader::read_num(id)));
+        }
+        count += 1;
+    }
+    if count == 4 {
+        Ok(corners)
+    } else {
+        Err(Error::InvalidTerrain(value.to_string()))
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Animation {
-    frame: Option<Frame>,
+    frames: Vec<Frame>,
 }
 
 impl Animation {
+    /// Returns the first frame of the animation.
     pub fn frame(&self) -> Option<&Frame> {
-        self.frame.as_ref()
+        self.frames.first()
     }
 
-    fn set_frame(&mut self, frame: Frame) {
-        self.frame = Some(frame);
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    fn add_frame(&mut self, frame: Frame) {
+        self.frames.push(frame);
     }
 }
 
@@ -347,8 +586,8 @@ impl Frame {
 #[derive(Debug, Default)]
 pub struct Terrain {
     name: String,
-    tile: String,
-    properties: PropertyCollection,
+    tile: Option<u32>,
+    properties: Properties,
 }
 
 impl Terrain {
@@ -360,19 +599,20 @@ impl Terrain {
         self.name = name.into();
     }
 
-    pub fn tile(&self) -> &str {
-        &self.tile
+    /// Returns the local id of the tile representing the terrain.
+    pub fn tile(&self) -> Option<u32> {
+        self.tile
     }
 
-    fn set_tile<S: Into<String>>(&mut self, tile: S) {
-        self.tile = tile.into();
+    fn set_tile(&mut self, tile: u32) {
+        self.tile = Some(tile);
     }
 
-    pub fn properties(&self) -> Properties {
-        self.properties.iter()
+    pub fn properties(&self) -> &Properties {
+        &self.properties
     }
 
-    fn set_properties(&mut self, properties: PropertyCollection) {
+    fn set_properties(&mut self, properties: Properties) {
         self.properties = properties;
     }
 }
@@ -387,9 +627,18 @@ impl<R: Read> ElementReader<Tileset> for TmxReader<R> {
             "source" => {
                 tileset.set_source(value);
             }
+            "version" => {
+                tileset.set_version(value);
+            }
+            "tiledversion" => {
+                tileset.set_tiled_version(value);
+            }
             "name" => {
                 tileset.set_name(value);
             }
+            "class" => {
+                tileset.set_class(value);
+            }
             "tilewidth" => {
                 let tile_width = try!(reader::read_num(value));
                 tileset.set_tile_width(tile_width);
@@ -475,7 +724,11 @@ impl<R: Read> ElementReader<Terrain> for TmxReader<R> {
                 terrain.set_name(value);
             }
             "tile" => {
-                terrain.set_tile(value);
+                let tile: i32 = try!(value.parse().map_err(|_| Error::InvalidTerrain(value.to_string())));
+                // Tiled writes -1 for a terrain without a representative tile
+                if tile >= 0 {
+                    terrain.set_tile(tile as u32);
+                }
             }
             _ => {
                 return Err(Error::UnknownAttribute(name.to_string()));
@@ -501,8 +754,8 @@ impl<R: Read> ElementReader<Tile> for TmxReader<R> {
                 tile.set_id(id);
             }
             "terrain" => {
-                let corners = try!(Corners::from_str(value));
-                tile.set_corners(corners);
+                let corners = try!(read_terrain_corners(value));
+                tile.set_terrain_corners(corners);
             }
             "probability" => {
                 let probability = try!(reader::read_num(value));
@@ -542,8 +795,8 @@ impl<R: Read> ElementReader<Tile> for TmxReader<R> {
     }
 }
 
-impl<R: Read> ElementReader<PropertyCollection> for TmxReader<R> {
-    fn read_children(&mut self, properties: &mut PropertyCollection, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
+impl<R: Read> ElementReader<Properties> for TmxReader<R> {
+    fn read_children(&mut self, properties: &mut Properties, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
         if name == "property" {
             let property = try!(self.on_property(attributes));
             properties.push(property);
@@ -566,7 +819,7 @@ impl<R: Read> ElementReader<Animation> for TmxReader<R> {
     fn read_children(&mut self, animation: &mut Animation, name: &str, attributes: &[OwnedAttribute]) -> ::Result<()>{
         if let "frame" = name {
             let frame = try!(self.on_frame(attributes));
-            animation.set_frame(frame);
+            animation.add_frame(frame);
         }
         Ok(())
     }
@@ -589,5 +842,9 @@ impl<R: Read> ElementReader<Frame> for TmxReader<R> {
         };
         Ok(())
     }
+
+    fn validate_attributes(&mut self, attributes: &[OwnedAttribute]) -> ::Result<()> {
+        reader::require_attributes(attributes, &["tileid", "duration"])
+    }
 }
 
@@ -1,5 +1,9 @@
 extern crate tmx;
 
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::str::FromStr;
+
 #[macro_use]
 extern crate assert_matches;
 
@@ -15,6 +19,15 @@ fn after_reading_valid_tmx_file_expect_valid_map() {
     assert_matches!(result, Ok(tmx::Map {..}));
 }
 
+#[test]
+fn after_reading_utf16_tmx_file_expect_valid_map() {
+    let map = tmx::Map::open("data/utf16_map.tmx").unwrap();
+    assert_eq!((2, 1), map.dimensions());
+    assert_eq!("Ünïcödé", map.properties().get("title").unwrap().value());
+    let gids: Vec<_> = map.layers().next().unwrap().data().unwrap().tiles().map(|tile| tile.gid).collect();
+    assert_eq!(vec![1, 2], gids);
+}
+
 #[test]
 fn when_reading_nonexistent_tileset_file_expect_io_error() {
     let result = tmx::Tileset::open("non_existent_file.tsx");
@@ -31,3 +44,73 @@ fn after_reading_valid_tsx_file_expect_valid_tileset() {
     assert_eq!(image.height(), 480);
 }
 
+
+#[test]
+fn when_reading_tsx_file_without_tile_size_expect_missing_attribute_error() {
+    let result = tmx::Tileset::open("data/tileset_without_tile_size.tsx");
+    assert_matches!(result, Err(tmx::Error::MissingAttribute(ref name)) if name == "tilewidth");
+}
+
+#[test]
+fn after_reading_tsx_file_from_reader_expect_same_tileset_as_from_str() {
+    let file = File::open("data/simple_tileset.tsx").unwrap();
+    let from_reader = tmx::Tileset::from_reader(BufReader::new(file)).unwrap();
+    let content = fs::read_to_string("data/simple_tileset.tsx").unwrap();
+    let from_str = tmx::Tileset::from_str(&content).unwrap();
+    assert_eq!("Bricks", from_reader.name());
+    assert_eq!(format!("{:?}", from_str), format!("{:?}", from_reader));
+}
+
+#[test]
+fn when_loading_tmx_file_expect_map() {
+    let result = tmx::load("data/empty_map.tmx");
+    assert_matches!(result, Ok(tmx::Loaded::Map(..)));
+}
+
+#[test]
+fn when_loading_tsx_file_expect_tileset() {
+    let result = tmx::load("data/simple_tileset.tsx");
+    assert_matches!(result, Ok(tmx::Loaded::Tileset(..)));
+}
+
+#[test]
+fn when_loading_file_with_unknown_extension_expect_extension_error() {
+    let result = tmx::load("data/unknown.png");
+    assert_matches!(result, Err(tmx::Error::BadFileExtension(..)));
+}
+
+#[test]
+fn after_reading_map_expect_core_types_at_crate_root() {
+    use tmx::{Color, Error, ImageLayer, Layer, Map, Object, ObjectGroup, Orientation, Property, RenderOrder, Tileset};
+
+    let map = Map::from_str(r##"<map orientation="isometric" backgroundcolor="#102030">
+        <properties><property name="title" value="root"/></properties>
+        <tileset firstgid="1" tilewidth="8" tileheight="8"/>
+        <layer name="ground"/>
+        <objectgroup><object id="1"/></objectgroup>
+        <imagelayer name="sky"/>
+    </map>"##).unwrap();
+    assert_eq!(Orientation::Isometric, map.orientation());
+    assert_eq!(RenderOrder::RightDown, map.render_order());
+    assert_eq!(Some(&Color(255, 16, 32, 48)), map.background_color());
+    let property: &Property = map.properties().get("title").unwrap();
+    assert_eq!("root", property.value());
+    let tileset: &Tileset = map.tilesets().next().unwrap();
+    assert_eq!(1, tileset.first_gid());
+    let layer: &Layer = map.layers().next().unwrap();
+    assert_eq!("ground", layer.name());
+    let object_group: &ObjectGroup = map.object_groups().next().unwrap();
+    let object: &Object = object_group.objects().next().unwrap();
+    assert_eq!(1, object.id());
+    let image_layer: &ImageLayer = map.image_layers().next().unwrap();
+    assert_eq!("sky", image_layer.name());
+    assert_matches!(Map::from_str("<map"), Err(Error::BadXml(..)));
+}
+
+#[test]
+fn after_reading_embedded_tmx_bytes_expect_valid_maps() {
+    let map = tmx::Map::from_bytes(include_bytes!("../data/utf16_map.tmx")).unwrap();
+    assert_eq!((2, 1), map.dimensions());
+    let map = tmx::Map::from_bytes(include_bytes!("../data/empty_map.tmx")).unwrap();
+    assert_eq!(0, map.layers().count());
+}